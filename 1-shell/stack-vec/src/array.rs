@@ -0,0 +1,539 @@
+use core::convert::TryFrom;
+use core::fmt;
+use core::iter::{DoubleEndedIterator, ExactSizeIterator, IntoIterator, Iterator};
+use core::mem::{ManuallyDrop, MaybeUninit};
+use core::ops::{Bound, Deref, DerefMut, RangeBounds};
+use core::ptr;
+use core::slice::{self, Iter, IterMut};
+
+use StackVec;
+
+/// A contiguous array type with inline storage for `N` elements.
+///
+/// `ArrayVec` offers the same interface as `StackVec`, but owns its storage
+/// instead of borrowing it. It has no lifetime parameter, so it can be
+/// returned from functions and stored in statics. Like `UninitStackVec`, only
+/// the first `len` slots ever hold live values, and elements are dropped when
+/// they're removed from the vector or when the vector itself is dropped.
+pub struct ArrayVec<T, const N: usize> {
+    storage: [MaybeUninit<T>; N],
+    len: usize,
+}
+
+impl<T, const N: usize> ArrayVec<T, N> {
+    /// Constructs a new, empty `ArrayVec<T, N>`. The returned `ArrayVec` will
+    /// be able to hold `N` values.
+    pub const fn new() -> ArrayVec<T, N> {
+        ArrayVec {
+            // An array of `MaybeUninit`s needs no initialization.
+            storage: unsafe { MaybeUninit::<[MaybeUninit<T>; N]>::uninit().assume_init() },
+            len: 0,
+        }
+    }
+
+    /// Returns the number of elements this vector can hold.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the number of elements in the vector, also referred to as its
+    /// 'length'.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the vector contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns true if the vector is at capacity.
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Extracts a slice containing the entire vector.
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.storage.as_ptr() as *const T, self.len) }
+    }
+
+    /// Extracts a mutable slice of the entire vector.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.storage.as_mut_ptr() as *mut T, self.len) }
+    }
+
+    /// Appends `value` to the back of this vector if the vector is not full.
+    ///
+    /// # Error
+    ///
+    /// If this vector is full, an `Err` is returned and `value` is dropped.
+    /// Otherwise, `Ok` is returned.
+    pub fn push(&mut self, value: T) -> Result<(), ()> {
+        if self.is_full() {
+            Err(())
+        } else {
+            self.storage[self.len] = MaybeUninit::new(value);
+            self.len += 1;
+            Ok(())
+        }
+    }
+
+    /// If this vector is not empty, removes the last element from this vector
+    /// and returns it. Otherwise returns `None`.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            None
+        } else {
+            self.len -= 1;
+            Some(unsafe { ptr::read(self.storage[self.len].as_ptr()) })
+        }
+    }
+
+    /// Inserts `value` at position `index`, shifting all elements after it to
+    /// the right.
+    ///
+    /// # Error
+    ///
+    /// If this vector is full, an `Err` is returned, `value` is dropped and
+    /// the vector is left unchanged. Otherwise, `Ok` is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, value: T) -> Result<(), ()> {
+        assert!(index <= self.len, "insertion index is out of bounds");
+        self.push(value)?;
+        self.as_mut_slice()[index..].rotate_right(1);
+        Ok(())
+    }
+
+    /// Removes element at index, shifting all remaining elements to the left.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "removal index is out of bounds");
+        self.as_mut_slice()[index..].rotate_left(1);
+        self.pop().unwrap()
+    }
+
+    /// Removes the element at `index` and returns it, replacing it with the
+    /// last element of the vector. This does not preserve ordering, but is
+    /// O(1).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "removal index is out of bounds");
+        let last = self.len - 1;
+        self.as_mut_slice().swap(index, last);
+        self.pop().unwrap()
+    }
+
+    /// Shortens the vector, keeping the first `len` elements and dropping the
+    /// rest. If `len` is greater than the vector's current length, this has no
+    /// effect.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            let tail = &mut self.as_mut_slice()[len..] as *mut [T];
+            // Shrink first so that a panicking `drop` can't lead to the tail
+            // being dropped a second time.
+            self.len = len;
+            unsafe { ptr::drop_in_place(tail) };
+        }
+    }
+
+    /// Clears the vector, dropping all values.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Retains only the elements for which `f` returns `true`, preserving the
+    /// order of the retained elements. The other elements are dropped.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        let mut kept = 0;
+        for i in 0..self.len {
+            if f(&self.as_slice()[i]) {
+                self.as_mut_slice().swap(kept, i);
+                kept += 1;
+            }
+        }
+        self.truncate(kept);
+    }
+
+    /// Removes all but the first of consecutive elements for which
+    /// `same_bucket(a, b)` returns `true`, where `a` is the later element.
+    pub fn dedup_by<F: FnMut(&mut T, &mut T) -> bool>(&mut self, mut same_bucket: F) {
+        if self.len <= 1 {
+            return;
+        }
+        let mut kept = 1;
+        for i in 1..self.len {
+            let (head, tail) = self.as_mut_slice().split_at_mut(i);
+            if !same_bucket(&mut tail[0], &mut head[kept - 1]) {
+                self.as_mut_slice().swap(kept, i);
+                kept += 1;
+            }
+        }
+        self.truncate(kept);
+    }
+
+    /// Removes all but the first of consecutive elements that resolve to the
+    /// same key.
+    pub fn dedup_by_key<K: PartialEq, F: FnMut(&mut T) -> K>(&mut self, mut key: F) {
+        self.dedup_by(|a, b| key(a) == key(b))
+    }
+
+    /// Appends every value yielded by `iter` to the back of this vector.
+    ///
+    /// # Error
+    ///
+    /// If `iter` yields more values than the vector has room for, an `Err` is
+    /// returned and the vector is restored to its previous length, dropping
+    /// the values that were appended. Otherwise, `Ok` is returned.
+    pub fn try_extend<I: IntoIterator<Item = T>>(&mut self, iter: I) -> Result<(), ()> {
+        let len = self.len;
+        for value in iter {
+            if self.push(value).is_err() {
+                self.truncate(len);
+                return Err(());
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits the vector in two at `at`, returning a new vector holding the
+    /// elements `[at, len)`. `self` is left holding the elements `[0, at)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > len`.
+    pub fn split_off(&mut self, at: usize) -> ArrayVec<T, N> {
+        assert!(at <= self.len, "split index is out of bounds");
+        let mut other = ArrayVec::new();
+        let moved = self.len - at;
+        unsafe {
+            let src = self.storage.as_ptr().add(at);
+            ptr::copy_nonoverlapping(src, other.storage.as_mut_ptr(), moved);
+        }
+        self.len = at;
+        other.len = moved;
+        other
+    }
+
+    /// Removes the elements in `range` from the vector and returns them as an
+    /// iterator. Elements after the range are shifted down to fill the gap
+    /// when the iterator is dropped. Elements of the range that weren't
+    /// yielded are dropped then too.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than its end or if the end
+    /// of the range is greater than the vector's length.
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> ArrayDrain<'_, T, N> {
+        let start = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&n) => n + 1,
+            Bound::Excluded(&n) => n,
+            Bound::Unbounded => self.len,
+        };
+        assert!(start <= end, "drain start is greater than its end");
+        assert!(end <= self.len, "drain end is out of bounds");
+
+        let orig_len = self.len;
+        // If the `ArrayDrain` is leaked, the drained elements and the tail
+        // are leaked with it rather than left behind half-moved.
+        self.len = start;
+        ArrayDrain {
+            vec: self,
+            front: start,
+            back: end,
+            tail: end,
+            orig_len,
+        }
+    }
+
+    /// Moves the elements of this vector into a new `StackVec` backed by
+    /// `storage`. The values they replace in `storage` are dropped.
+    ///
+    /// # Error
+    ///
+    /// If `storage` can't hold every element, `self` is returned unchanged as
+    /// the `Err` value.
+    pub fn into_stack_vec(self, storage: &mut [T]) -> Result<StackVec<'_, T>, Self> {
+        if storage.len() < self.len {
+            return Err(self);
+        }
+        let len = self.len;
+        for (slot, value) in storage.iter_mut().zip(self) {
+            *slot = value;
+        }
+        Ok(StackVec::with_len(storage, len))
+    }
+}
+
+impl<T: PartialEq, const N: usize> ArrayVec<T, N> {
+    /// Removes consecutive repeated elements according to `PartialEq`.
+    pub fn dedup(&mut self) {
+        self.dedup_by(|a, b| a == b)
+    }
+}
+
+impl<T: Clone, const N: usize> ArrayVec<T, N> {
+    /// Clones and appends every element of `other` to the back of this vector.
+    ///
+    /// # Error
+    ///
+    /// If the vector doesn't have room for all of `other`, an `Err` is
+    /// returned and the vector is left unchanged. Otherwise, `Ok` is returned.
+    pub fn extend_from_slice(&mut self, other: &[T]) -> Result<(), ()> {
+        if other.len() > N - self.len {
+            return Err(());
+        }
+        self.try_extend(other.iter().cloned())
+    }
+
+    /// Resizes the vector so that its length is `new_len`. If the vector
+    /// grows, the new slots are filled with clones of `value`; if it shrinks,
+    /// it is truncated.
+    ///
+    /// # Error
+    ///
+    /// If `new_len` is greater than the vector's capacity, an `Err` is
+    /// returned and the vector is left unchanged. Otherwise, `Ok` is returned.
+    pub fn resize(&mut self, new_len: usize, value: T) -> Result<(), ()> {
+        if new_len > N {
+            return Err(());
+        }
+        while self.len < new_len {
+            self.push(value.clone())?;
+        }
+        self.truncate(new_len);
+        Ok(())
+    }
+}
+
+impl<T, const N: usize> Drop for ArrayVec<T, N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T, const N: usize> Default for ArrayVec<T, N> {
+    fn default() -> ArrayVec<T, N> {
+        ArrayVec::new()
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for ArrayVec<T, N> {
+    fn clone(&self) -> ArrayVec<T, N> {
+        let mut clone = ArrayVec::new();
+        clone.extend_from_slice(self).expect("same capacity");
+        clone
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for ArrayVec<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, const N: usize> PartialEq for ArrayVec<T, N> {
+    fn eq(&self, other: &ArrayVec<T, N>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for ArrayVec<T, N> {}
+
+impl<T, const N: usize> Deref for ArrayVec<T, N> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T, const N: usize> DerefMut for ArrayVec<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for ArrayVec<T, N> {
+    fn from(array: [T; N]) -> ArrayVec<T, N> {
+        let mut vec = ArrayVec::new();
+        vec.try_extend(IntoIterator::into_iter(array)).ok();
+        vec
+    }
+}
+
+impl<'a, T: Clone, const N: usize> TryFrom<&'a [T]> for ArrayVec<T, N> {
+    type Error = ();
+
+    /// Clones the elements of `slice` into a new `ArrayVec`, failing if there
+    /// are more than `N` of them.
+    fn try_from(slice: &'a [T]) -> Result<ArrayVec<T, N>, ()> {
+        let mut vec = ArrayVec::new();
+        vec.extend_from_slice(slice)?;
+        Ok(vec)
+    }
+}
+
+impl<'a, T: Clone, const N: usize> TryFrom<StackVec<'a, T>> for ArrayVec<T, N> {
+    type Error = StackVec<'a, T>;
+
+    /// Clones the elements of `vec` into a new `ArrayVec`, failing with `vec`
+    /// if there are more than `N` of them. `vec` only borrows its storage, so
+    /// the originals are left in place.
+    fn try_from(vec: StackVec<'a, T>) -> Result<ArrayVec<T, N>, StackVec<'a, T>> {
+        if vec.len() > N {
+            return Err(vec);
+        }
+        let mut array = ArrayVec::new();
+        array.extend_from_slice(&vec).ok();
+        Ok(array)
+    }
+}
+
+impl<T, const N: usize> IntoIterator for ArrayVec<T, N> {
+    type Item = T;
+    type IntoIter = ArrayIntoIter<T, N>;
+    fn into_iter(self) -> Self::IntoIter {
+        // The iterator takes over responsibility for dropping the elements.
+        let this = ManuallyDrop::new(self);
+        ArrayIntoIter {
+            storage: unsafe { ptr::read(&this.storage) },
+            front: 0,
+            back: this.len,
+        }
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a ArrayVec<T, N> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_slice().iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut ArrayVec<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_mut_slice().iter_mut()
+    }
+}
+
+/// An iterator that moves elements out of an `ArrayVec`. Elements that
+/// haven't been yielded are dropped along with the iterator.
+///
+/// This `struct` is created by the `into_iter` method on `ArrayVec` (provided
+/// by the `IntoIterator` trait).
+pub struct ArrayIntoIter<T, const N: usize> {
+    storage: [MaybeUninit<T>; N],
+    front: usize,
+    back: usize,
+}
+
+impl<T, const N: usize> Iterator for ArrayIntoIter<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.front == self.back {
+            None
+        } else {
+            self.front += 1;
+            Some(unsafe { ptr::read(self.storage[self.front - 1].as_ptr()) })
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T, const N: usize> DoubleEndedIterator for ArrayIntoIter<T, N> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.front == self.back {
+            None
+        } else {
+            self.back -= 1;
+            Some(unsafe { ptr::read(self.storage[self.back].as_ptr()) })
+        }
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for ArrayIntoIter<T, N> {}
+
+impl<T, const N: usize> Drop for ArrayIntoIter<T, N> {
+    fn drop(&mut self) {
+        for _ in self.by_ref() {}
+    }
+}
+
+/// An iterator that removes a range of elements from an `ArrayVec` and yields
+/// them by value.
+///
+/// This `struct` is created by the `drain` method on `ArrayVec`.
+pub struct ArrayDrain<'b, T: 'b, const N: usize> {
+    vec: &'b mut ArrayVec<T, N>,
+    front: usize,
+    back: usize,
+    tail: usize,
+    orig_len: usize,
+}
+
+impl<'b, T: 'b, const N: usize> Iterator for ArrayDrain<'b, T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.front == self.back {
+            None
+        } else {
+            self.front += 1;
+            Some(unsafe { ptr::read(self.vec.storage[self.front - 1].as_ptr()) })
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<'b, T: 'b, const N: usize> DoubleEndedIterator for ArrayDrain<'b, T, N> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.front == self.back {
+            None
+        } else {
+            self.back -= 1;
+            Some(unsafe { ptr::read(self.vec.storage[self.back].as_ptr()) })
+        }
+    }
+}
+
+impl<'b, T: 'b, const N: usize> ExactSizeIterator for ArrayDrain<'b, T, N> {}
+
+impl<'b, T: 'b, const N: usize> Drop for ArrayDrain<'b, T, N> {
+    fn drop(&mut self) {
+        // Drop anything that wasn't yielded, then close the gap.
+        for _ in self.by_ref() {}
+
+        let start = self.vec.len;
+        let tail_len = self.orig_len - self.tail;
+        unsafe {
+            let base = self.vec.storage.as_mut_ptr();
+            ptr::copy(base.add(self.tail), base.add(start), tail_len);
+        }
+        self.vec.len = start + tail_len;
+    }
+}
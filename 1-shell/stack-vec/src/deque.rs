@@ -0,0 +1,294 @@
+use core::fmt;
+use core::iter::{Chain, IntoIterator};
+use core::mem::MaybeUninit;
+use core::ops::{Index, IndexMut};
+use core::ptr;
+use core::slice::{self, Iter, IterMut};
+
+/// An iterator over the elements of a `StackDeque`, front to back.
+pub type DequeIter<'b, T> = Chain<Iter<'b, T>, Iter<'b, T>>;
+
+/// A mutable iterator over the elements of a `StackDeque`, front to back.
+pub type DequeIterMut<'b, T> = Chain<IterMut<'b, T>, IterMut<'b, T>>;
+
+/// A double-ended queue implemented as a ring buffer over a slice.
+///
+/// Like `UninitStackVec`, `StackDeque` requires no memory allocation: it is
+/// backed by a user-supplied slice of possibly uninitialized memory that bounds
+/// its capacity. Values can be pushed and popped at either end in O(1). Only
+/// the slots between the front and the back ever hold live values, so popped
+/// values leave nothing behind, and elements still in the deque are dropped
+/// when it's cleared or dropped.
+///
+/// A deque created with `new` rejects pushes once it is full. One created with
+/// `overwriting` instead makes room by dropping the element at the opposite
+/// end, so that, for instance, `push_back` discards the oldest element. This is
+/// the behavior wanted for a bounded history.
+pub struct StackDeque<'a, T: 'a> {
+    storage: &'a mut [MaybeUninit<T>],
+    /// Index in `storage` of the front element.
+    head: usize,
+    len: usize,
+    overwrite: bool,
+}
+
+impl<'a, T: 'a> StackDeque<'a, T> {
+    /// Constructs a new, empty `StackDeque<T>` using `storage` as the backing
+    /// store. The returned `StackDeque` will be able to hold `storage.len()`
+    /// values; pushing onto a full deque fails.
+    pub fn new(storage: &'a mut [MaybeUninit<T>]) -> StackDeque<'a, T> {
+        StackDeque {
+            storage,
+            head: 0,
+            len: 0,
+            overwrite: false,
+        }
+    }
+
+    /// Constructs a new, empty `StackDeque<T>` using `storage` as the backing
+    /// store. The returned `StackDeque` will be able to hold `storage.len()`
+    /// values; pushing onto a full deque drops the element at the other end
+    /// to make room.
+    pub fn overwriting(storage: &'a mut [MaybeUninit<T>]) -> StackDeque<'a, T> {
+        let mut deque = StackDeque::new(storage);
+        deque.overwrite = true;
+        deque
+    }
+
+    /// Returns true if pushing onto a full deque overwrites the element at the
+    /// other end.
+    pub fn is_overwriting(&self) -> bool {
+        self.overwrite
+    }
+
+    /// Returns the number of elements this deque can hold.
+    pub fn capacity(&self) -> usize {
+        self.storage.len()
+    }
+
+    /// Returns the number of elements in the deque.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the deque contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns true if the deque is at capacity.
+    pub fn is_full(&self) -> bool {
+        self.len == self.storage.len()
+    }
+
+    /// Maps a position in the deque to an index in `storage`.
+    fn wrap(&self, i: usize) -> usize {
+        let i = self.head + i;
+        if i >= self.capacity() {
+            i - self.capacity()
+        } else {
+            i
+        }
+    }
+
+    /// Appends `value` to the back of this deque. If the deque is full and
+    /// overwriting, the front element is dropped to make room.
+    ///
+    /// # Error
+    ///
+    /// If this deque is full and not overwriting, or has no capacity at all,
+    /// an `Err` is returned. Otherwise, `Ok` is returned.
+    pub fn push_back(&mut self, value: T) -> Result<(), ()> {
+        if self.is_full() {
+            if !self.overwrite || self.capacity() == 0 {
+                return Err(());
+            }
+            self.pop_front();
+        }
+        let i = self.wrap(self.len);
+        self.storage[i] = MaybeUninit::new(value);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Prepends `value` to the front of this deque. If the deque is full and
+    /// overwriting, the back element is dropped to make room.
+    ///
+    /// # Error
+    ///
+    /// If this deque is full and not overwriting, or has no capacity at all,
+    /// an `Err` is returned. Otherwise, `Ok` is returned.
+    pub fn push_front(&mut self, value: T) -> Result<(), ()> {
+        if self.is_full() {
+            if !self.overwrite || self.capacity() == 0 {
+                return Err(());
+            }
+            self.pop_back();
+        }
+        self.head = self.wrap(self.capacity() - 1);
+        self.storage[self.head] = MaybeUninit::new(value);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Removes the front element and returns it, or `None` if the deque is
+    /// empty.
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        let value = unsafe { ptr::read(self.storage[self.head].as_ptr()) };
+        self.head = self.wrap(1);
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// Removes the back element and returns it, or `None` if the deque is
+    /// empty.
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        self.len -= 1;
+        let i = self.wrap(self.len);
+        Some(unsafe { ptr::read(self.storage[i].as_ptr()) })
+    }
+
+    /// Returns a reference to the element at position `i` from the front, or
+    /// `None` if `i` is out of bounds.
+    pub fn get(&self, i: usize) -> Option<&T> {
+        if i < self.len {
+            Some(unsafe { &*self.storage[self.wrap(i)].as_ptr() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the element at position `i` from the
+    /// front, or `None` if `i` is out of bounds.
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        if i < self.len {
+            let i = self.wrap(i);
+            Some(unsafe { &mut *self.storage[i].as_mut_ptr() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a reference to the front element, or `None` if the deque is
+    /// empty.
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    /// Returns a reference to the back element, or `None` if the deque is
+    /// empty.
+    pub fn back(&self) -> Option<&T> {
+        self.len.checked_sub(1).and_then(|i| self.get(i))
+    }
+
+    /// Returns the contents of the deque as two slices which, taken in order,
+    /// hold the elements front to back.
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let (front, back) = if self.head + self.len <= self.capacity() {
+            (&self.storage[self.head..self.head + self.len], &[][..])
+        } else {
+            let (wrapped, front) = self.storage.split_at(self.head);
+            (front, &wrapped[..self.wrap(self.len)])
+        };
+        unsafe { (assume_init(front), assume_init(back)) }
+    }
+
+    /// Returns the contents of the deque as two mutable slices which, taken in
+    /// order, hold the elements front to back.
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let (front, back) = if self.head + self.len <= self.capacity() {
+            let (head, len) = (self.head, self.len);
+            (&mut self.storage[head..head + len], &mut [][..])
+        } else {
+            let end = self.wrap(self.len);
+            let (wrapped, front) = self.storage.split_at_mut(self.head);
+            (front, &mut wrapped[..end])
+        };
+        unsafe { (assume_init_mut(front), assume_init_mut(back)) }
+    }
+
+    /// Returns an iterator over the elements, front to back.
+    pub fn iter(&self) -> DequeIter<'_, T> {
+        let (front, back) = self.as_slices();
+        front.iter().chain(back.iter())
+    }
+
+    /// Returns an iterator over mutable references to the elements, front to
+    /// back.
+    pub fn iter_mut(&mut self) -> DequeIterMut<'_, T> {
+        let (front, back) = self.as_mut_slices();
+        front.iter_mut().chain(back.iter_mut())
+    }
+
+    /// Removes all elements from the deque, dropping them.
+    pub fn clear(&mut self) {
+        let (front, back) = self.as_mut_slices();
+        let (front, back) = (front as *mut [T], back as *mut [T]);
+        // Empty the deque first so that a panicking `drop` can't lead to an
+        // element being dropped a second time.
+        self.head = 0;
+        self.len = 0;
+        unsafe {
+            ptr::drop_in_place(front);
+            ptr::drop_in_place(back);
+        }
+    }
+}
+
+/// Views `slots`, all of which hold live values, as a slice of those values.
+unsafe fn assume_init<T>(slots: &[MaybeUninit<T>]) -> &[T] {
+    slice::from_raw_parts(slots.as_ptr() as *const T, slots.len())
+}
+
+/// Views `slots`, all of which hold live values, as a mutable slice of those
+/// values.
+unsafe fn assume_init_mut<T>(slots: &mut [MaybeUninit<T>]) -> &mut [T] {
+    slice::from_raw_parts_mut(slots.as_mut_ptr() as *mut T, slots.len())
+}
+
+impl<'a, T: 'a> Drop for StackDeque<'a, T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for StackDeque<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T> Index<usize> for StackDeque<'a, T> {
+    type Output = T;
+    fn index(&self, i: usize) -> &T {
+        self.get(i).expect("deque index is out of bounds")
+    }
+}
+
+impl<'a, T> IndexMut<usize> for StackDeque<'a, T> {
+    fn index_mut(&mut self, i: usize) -> &mut T {
+        self.get_mut(i).expect("deque index is out of bounds")
+    }
+}
+
+impl<'a, 'b, T> IntoIterator for &'a StackDeque<'b, T> {
+    type Item = &'a T;
+    type IntoIter = DequeIter<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, 'b, T> IntoIterator for &'a mut StackDeque<'b, T> {
+    type Item = &'a mut T;
+    type IntoIter = DequeIterMut<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
@@ -0,0 +1,191 @@
+use core::iter::{DoubleEndedIterator, ExactSizeIterator, Iterator};
+use core::ops::{Range, RangeFrom, RangeFull, RangeTo};
+
+use {rotate_left, take, StackVec};
+
+/// A range of indices into a `StackVec`: `a..b`, `a..`, `..b` or `..`.
+///
+/// This trait is taken by `StackVec::drain` in place of
+/// `core::ops::RangeBounds`, which the toolchain that builds the kernel
+/// doesn't have.
+pub trait IndexRange {
+    /// Returns the first index in the range, if the range has a start.
+    fn start(&self) -> Option<usize>;
+
+    /// Returns one past the last index in the range, if the range has an end.
+    fn end(&self) -> Option<usize>;
+}
+
+impl IndexRange for Range<usize> {
+    fn start(&self) -> Option<usize> {
+        Some(self.start)
+    }
+    fn end(&self) -> Option<usize> {
+        Some(self.end)
+    }
+}
+
+impl IndexRange for RangeFrom<usize> {
+    fn start(&self) -> Option<usize> {
+        Some(self.start)
+    }
+    fn end(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl IndexRange for RangeTo<usize> {
+    fn start(&self) -> Option<usize> {
+        None
+    }
+    fn end(&self) -> Option<usize> {
+        Some(self.end)
+    }
+}
+
+impl IndexRange for RangeFull {
+    fn start(&self) -> Option<usize> {
+        None
+    }
+    fn end(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// An iterator that moves the elements out of a `StackVec`, leaving
+/// `T::default()` in their place.
+///
+/// This `struct` is created by the `into_iter` method on `StackVec` (provided
+/// by the `IntoIterator` trait).
+#[derive(Debug)]
+pub struct IntoIter<'a, T: 'a> {
+    storage: &'a mut [T],
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T: 'a> IntoIter<'a, T> {
+    pub(crate) fn new(vec: StackVec<'a, T>) -> IntoIter<'a, T> {
+        let back = vec.len;
+        IntoIter {
+            storage: vec.storage,
+            front: 0,
+            back,
+        }
+    }
+
+    /// Returns the remaining elements as a slice.
+    pub fn as_slice(&self) -> &[T] {
+        &self.storage[self.front..self.back]
+    }
+}
+
+impl<'a, T: Default + 'a> Iterator for IntoIter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.front == self.back {
+            None
+        } else {
+            self.front += 1;
+            Some(take(&mut self.storage[self.front - 1]))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<'a, T: Default + 'a> DoubleEndedIterator for IntoIter<'a, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.front == self.back {
+            None
+        } else {
+            self.back -= 1;
+            Some(take(&mut self.storage[self.back]))
+        }
+    }
+}
+
+impl<'a, T: Default + 'a> ExactSizeIterator for IntoIter<'a, T> {}
+
+/// An iterator that removes a range of elements from a `StackVec` and yields
+/// them, leaving `T::default()` in their place.
+///
+/// This `struct` is created by the `drain` method on `StackVec`.
+#[derive(Debug)]
+pub struct Drain<'b, 'a: 'b, T: 'a> {
+    vec: &'b mut StackVec<'a, T>,
+    /// Index of the next element to yield from the front.
+    front: usize,
+    /// One past the index of the next element to yield from the back.
+    back: usize,
+    /// Index of the first element after the drained range.
+    tail: usize,
+    /// Length of the vector when the `Drain` was created.
+    orig_len: usize,
+}
+
+impl<'b, 'a: 'b, T: 'a> Drain<'b, 'a, T> {
+    pub(crate) fn new(vec: &'b mut StackVec<'a, T>, start: usize, end: usize) -> Drain<'b, 'a, T> {
+        let orig_len = vec.len;
+        // If the `Drain` is leaked, the drained elements and the tail are
+        // forgotten rather than left behind half-removed.
+        vec.len = start;
+        Drain {
+            vec,
+            front: start,
+            back: end,
+            tail: end,
+            orig_len,
+        }
+    }
+
+    /// Returns the elements that have yet to be yielded as a slice.
+    pub fn as_slice(&self) -> &[T] {
+        &self.vec.storage[self.front..self.back]
+    }
+}
+
+impl<'b, 'a: 'b, T: Default + 'a> Iterator for Drain<'b, 'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.front == self.back {
+            None
+        } else {
+            self.front += 1;
+            Some(take(&mut self.vec.storage[self.front - 1]))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<'b, 'a: 'b, T: Default + 'a> DoubleEndedIterator for Drain<'b, 'a, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.front == self.back {
+            None
+        } else {
+            self.back -= 1;
+            Some(take(&mut self.vec.storage[self.back]))
+        }
+    }
+}
+
+impl<'b, 'a: 'b, T: Default + 'a> ExactSizeIterator for Drain<'b, 'a, T> {}
+
+impl<'b, 'a: 'b, T: 'a> Drop for Drain<'b, 'a, T> {
+    fn drop(&mut self) {
+        // Close the gap by rotating the drained elements past the tail.
+        let start = self.vec.len;
+        let tail_len = self.orig_len - self.tail;
+        rotate_left(&mut self.vec.storage[start..self.orig_len], self.tail - start);
+        self.vec.len = start + tail_len;
+    }
+}
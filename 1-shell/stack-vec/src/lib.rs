@@ -2,12 +2,39 @@
 #![feature(pointer_methods)]
 #![no_std]
 
+#[cfg(test)]
+extern crate std;
+
+#[cfg(feature = "uninit")]
+mod array;
+#[cfg(feature = "uninit")]
+mod deque;
+mod iter;
+#[cfg(feature = "uninit")]
+mod map;
+mod spsc;
+mod string;
 #[cfg(test)]
 mod tests;
+#[cfg(feature = "uninit")]
+mod uninit;
+
+#[cfg(feature = "uninit")]
+pub use array::{ArrayDrain, ArrayIntoIter, ArrayVec};
+#[cfg(feature = "uninit")]
+pub use deque::{DequeIter, DequeIterMut, StackDeque};
+pub use iter::{Drain, IndexRange, IntoIter};
+#[cfg(feature = "uninit")]
+pub use map::{Entry, MapIter, MapIterMut, OccupiedEntry, StackMap, VacantEntry};
+pub use spsc::{Consumer, Producer, SpscDeque};
+pub use string::StackString;
+#[cfg(feature = "uninit")]
+pub use uninit::{UninitIntoIter, UninitStackVec};
 
 use core::iter::IntoIterator;
+use core::mem;
 use core::ops::{Deref, DerefMut};
-use core::slice::Iter;
+use core::slice::{Iter, IterMut};
 
 /// A contiguous array type backed by a slice.
 ///
@@ -17,6 +44,11 @@ use core::slice::Iter;
 /// result, `StackVec`'s capacity is _bounded_ by the user-supplied slice. This
 /// results in `push` being fallible: if `push` is called when the vector is
 /// full, an `Err` is returned.
+///
+/// The backing slice belongs to the caller, so methods that return elements
+/// by value, such as `pop` and `drain`, move them out and leave
+/// `T::default()` in their place. Use `UninitStackVec` or `ArrayVec` for
+/// elements that have no default value.
 #[derive(Debug)]
 pub struct StackVec<'a, T: 'a> {
     storage: &'a mut [T],
@@ -49,22 +81,6 @@ impl<'a, T: 'a> StackVec<'a, T> {
         self.storage.len()
     }
 
-    /// Removes element at index, shifting all remaining elements to the left
-    pub fn remove(&mut self, index: usize) -> T {
-        let len = self.len();
-        assert!(index < len);
-        unsafe {
-            let ret;
-            {
-                let ptr = self.storage.as_mut_ptr().add(index);
-                ret = core::ptr::read(ptr);
-                core::ptr::copy(ptr.offset(1), ptr, len - index - 1);
-            }
-            self.len -= 1;
-            ret
-        }
-    }
-
     /// Shortens the vector, keeping the first `len` elements. If `len` is
     /// greater than the vector's current length, this has no effect. Note that
     /// this method has no effect on the capacity of the vector.
@@ -74,6 +90,12 @@ impl<'a, T: 'a> StackVec<'a, T> {
         }
     }
 
+    /// Clears the vector, removing all values. Note that this method has no
+    /// effect on the capacity of the vector.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
     /// Extracts a slice containing the entire vector, consuming `self`.
     ///
     /// Note that the returned slice's length will be the length of this vector,
@@ -123,21 +145,231 @@ impl<'a, T: 'a> StackVec<'a, T> {
             Ok(())
         }
     }
+
+    /// Inserts `value` at position `index`, shifting all elements after it to
+    /// the right.
+    ///
+    /// # Error
+    ///
+    /// If this vector is full, an `Err` is returned and the vector is left
+    /// unchanged. Otherwise, `Ok` is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, value: T) -> Result<(), ()> {
+        assert!(index <= self.len, "insertion index is out of bounds");
+        self.push(value)?;
+        rotate_right(&mut self.as_mut_slice()[index..], 1);
+        Ok(())
+    }
+
+    /// Retains only the elements for which `f` returns `true`, preserving the
+    /// order of the retained elements.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        let mut kept = 0;
+        for i in 0..self.len {
+            if f(&self.storage[i]) {
+                self.storage.swap(kept, i);
+                kept += 1;
+            }
+        }
+        self.truncate(kept);
+    }
+
+    /// Removes all but the first of consecutive elements for which
+    /// `same_bucket(a, b)` returns `true`, where `a` is the later element.
+    pub fn dedup_by<F: FnMut(&mut T, &mut T) -> bool>(&mut self, mut same_bucket: F) {
+        if self.len <= 1 {
+            return;
+        }
+        let mut kept = 1;
+        for i in 1..self.len {
+            let same = {
+                let (head, tail) = self.storage.split_at_mut(i);
+                same_bucket(&mut tail[0], &mut head[kept - 1])
+            };
+            if !same {
+                self.storage.swap(kept, i);
+                kept += 1;
+            }
+        }
+        self.truncate(kept);
+    }
+
+    /// Removes all but the first of consecutive elements that resolve to the
+    /// same key.
+    pub fn dedup_by_key<K: PartialEq, F: FnMut(&mut T) -> K>(&mut self, mut key: F) {
+        self.dedup_by(|a, b| key(a) == key(b))
+    }
+
+    /// Appends every value yielded by `iter` to the back of this vector.
+    ///
+    /// # Error
+    ///
+    /// If `iter` yields more values than the vector has room for, an `Err` is
+    /// returned and the vector is restored to its previous length. Otherwise,
+    /// `Ok` is returned.
+    pub fn try_extend<I: IntoIterator<Item = T>>(&mut self, iter: I) -> Result<(), ()> {
+        let len = self.len;
+        for value in iter {
+            if self.push(value).is_err() {
+                self.truncate(len);
+                return Err(());
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits the vector in two at `at`, moving the elements `[at, len)` into
+    /// a new vector backed by `storage`. `self` is left holding the elements
+    /// `[0, at)`.
+    ///
+    /// # Error
+    ///
+    /// If `storage` can't hold `len - at` elements, an `Err` is returned and
+    /// the vector is left unchanged. Otherwise, the new vector is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > len`.
+    pub fn split_off<'b>(
+        &mut self,
+        at: usize,
+        storage: &'b mut [T],
+    ) -> Result<StackVec<'b, T>, ()> {
+        assert!(at <= self.len, "split index is out of bounds");
+        let moved = self.len - at;
+        if moved > storage.len() {
+            return Err(());
+        }
+        for (dst, src) in storage.iter_mut().zip(&mut self.storage[at..self.len]) {
+            mem::swap(dst, src);
+        }
+        self.len = at;
+        Ok(StackVec::with_len(storage, moved))
+    }
 }
 
-impl<'a, T: Clone + 'a> StackVec<'a, T> {
+impl<'a, T: PartialEq + 'a> StackVec<'a, T> {
+    /// Removes consecutive repeated elements according to `PartialEq`.
+    pub fn dedup(&mut self) {
+        self.dedup_by(|a, b| a == b)
+    }
+}
+
+impl<'a, T: Default + 'a> StackVec<'a, T> {
     /// If this vector is not empty, removes the last element from this vector
-    /// by cloning it and returns it. Otherwise returns `None`.
+    /// and returns it. Otherwise returns `None`.
     pub fn pop(&mut self) -> Option<T> {
         if self.is_empty() {
             None
         } else {
             self.len -= 1;
-            Some(self.storage[self.len].clone())
+            Some(take(&mut self.storage[self.len]))
+        }
+    }
+
+    /// Removes the element at `index` and returns it, shifting all elements
+    /// after it to the left.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "removal index is out of bounds");
+        rotate_left(&mut self.as_mut_slice()[index..], 1);
+        self.pop().unwrap()
+    }
+
+    /// Removes the element at `index` and returns it, replacing it with the
+    /// last element of the vector. This does not preserve ordering, but is
+    /// O(1).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "removal index is out of bounds");
+        let last = self.len - 1;
+        self.as_mut_slice().swap(index, last);
+        self.pop().unwrap()
+    }
+
+    /// Removes the elements in `range` from the vector and returns them as an
+    /// iterator. Elements after the range are shifted down to fill the gap
+    /// when the iterator is dropped, even if it wasn't fully consumed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than its end or if the end
+    /// of the range is greater than the vector's length.
+    pub fn drain<'b, R: IndexRange>(&'b mut self, range: R) -> Drain<'b, 'a, T> {
+        let start = range.start().unwrap_or(0);
+        let end = range.end().unwrap_or(self.len);
+        assert!(start <= end, "drain start is greater than its end");
+        assert!(end <= self.len, "drain end is out of bounds");
+        Drain::new(self, start, end)
+    }
+}
+
+impl<'a, T: Clone + 'a> StackVec<'a, T> {
+    /// Clones and appends every element of `other` to the back of this vector.
+    ///
+    /// # Error
+    ///
+    /// If the vector doesn't have room for all of `other`, an `Err` is
+    /// returned and the vector is left unchanged. Otherwise, `Ok` is returned.
+    pub fn extend_from_slice(&mut self, other: &[T]) -> Result<(), ()> {
+        if other.len() > self.capacity() - self.len {
+            return Err(());
+        }
+        self.try_extend(other.iter().cloned())
+    }
+
+    /// Resizes the vector so that its length is `new_len`. If the vector
+    /// grows, the new slots are filled with clones of `value`; if it shrinks,
+    /// it is truncated.
+    ///
+    /// # Error
+    ///
+    /// If `new_len` is greater than the vector's capacity, an `Err` is
+    /// returned and the vector is left unchanged. Otherwise, `Ok` is returned.
+    pub fn resize(&mut self, new_len: usize, value: T) -> Result<(), ()> {
+        if new_len > self.capacity() {
+            return Err(());
         }
+        while self.len < new_len {
+            self.push(value.clone())?;
+        }
+        self.truncate(new_len);
+        Ok(())
     }
 }
 
+// Stand-ins for `mem::take` and `<[T]>::rotate_left`/`rotate_right`, which the
+// nightly that builds the kernel doesn't have yet.
+
+/// Moves the value out of `slot`, leaving `T::default()` in its place.
+pub(crate) fn take<T: Default>(slot: &mut T) -> T {
+    let mut value = T::default();
+    mem::swap(slot, &mut value);
+    value
+}
+
+/// Rotates `slice` in place so that the element at `mid` comes first.
+pub(crate) fn rotate_left<T>(slice: &mut [T], mid: usize) {
+    slice[..mid].reverse();
+    slice[mid..].reverse();
+    slice.reverse();
+}
+
+/// Rotates `slice` in place so that its last `k` elements come first.
+pub(crate) fn rotate_right<T>(slice: &mut [T], k: usize) {
+    let mid = slice.len() - k;
+    rotate_left(slice, mid);
+}
+
 // FIXME: Implement `Deref`, `DerefMut`, and `IntoIterator` for `StackVec`.
 impl<'a, T> Deref for StackVec<'a, T> {
     type Target = [T];
@@ -152,18 +384,26 @@ impl<'a, T> DerefMut for StackVec<'a, T> {
     }
 }
 
-impl<'a, T> IntoIterator for StackVec<'a, T> {
-    type Item = &'a T;
-    type IntoIter = Iter<'a, T>;
+impl<'a, T: Default> IntoIterator for StackVec<'a, T> {
+    type Item = T;
+    type IntoIter = IntoIter<'a, T>;
     fn into_iter(self) -> Self::IntoIter {
-        self.storage[..].into_iter()
+        IntoIter::new(self)
     }
 }
-// FIXME: Implement IntoIterator` for `&StackVec`.
-impl<'a, T> IntoIterator for &'a StackVec<'a, T> {
+
+impl<'a, 'b, T> IntoIterator for &'a StackVec<'b, T> {
     type Item = &'a T;
     type IntoIter = Iter<'a, T>;
     fn into_iter(self) -> Self::IntoIter {
-        self.storage[..].into_iter()
+        self.as_slice().iter()
+    }
+}
+
+impl<'a, 'b, T> IntoIterator for &'a mut StackVec<'b, T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_mut_slice().iter_mut()
     }
 }
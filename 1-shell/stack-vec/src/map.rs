@@ -0,0 +1,488 @@
+use core::borrow::Borrow;
+use core::fmt;
+use core::iter::{DoubleEndedIterator, ExactSizeIterator, IntoIterator, Iterator};
+use core::mem::{self, MaybeUninit};
+use core::ops::{Bound, Index, RangeBounds};
+use core::slice;
+
+use UninitStackVec;
+
+/// An ordered map from keys to values backed by a slice of pairs.
+///
+/// `StackMap` requires no memory allocation: its entries are kept sorted by
+/// key in an `UninitStackVec<(K, V)>`, so its capacity is bounded by the
+/// user-supplied slice. Lookups are binary searches and take O(log n);
+/// insertions and removals shift the entries after them and take O(n), which
+/// is fine for the small tables a kernel needs before it has an allocator.
+/// Entries still in the map are dropped along with it.
+pub struct StackMap<'a, K: 'a, V: 'a> {
+    vec: UninitStackVec<'a, (K, V)>,
+}
+
+impl<'a, K: Ord + 'a, V: 'a> StackMap<'a, K, V> {
+    /// Constructs a new, empty `StackMap<K, V>` using `storage` as the backing
+    /// store. The returned `StackMap` will be able to hold `storage.len()`
+    /// entries. Any values already in `storage` are ignored.
+    pub fn new(storage: &'a mut [MaybeUninit<(K, V)>]) -> StackMap<'a, K, V> {
+        StackMap {
+            vec: UninitStackVec::new(storage),
+        }
+    }
+
+    /// Returns the number of entries this map can hold.
+    pub fn capacity(&self) -> usize {
+        self.vec.capacity()
+    }
+
+    /// Returns the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    /// Returns true if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    /// Returns true if the map is at capacity.
+    pub fn is_full(&self) -> bool {
+        self.vec.is_full()
+    }
+
+    /// Searches for `key`. Returns `Ok` with the index of its entry if it is
+    /// present, or `Err` with the index its entry would be inserted at.
+    fn search<Q: ?Sized + Ord>(&self, key: &Q) -> Result<usize, usize>
+    where
+        K: Borrow<Q>,
+    {
+        self.vec.binary_search_by(|entry| entry.0.borrow().cmp(key))
+    }
+
+    /// Returns a reference to the value for `key`, or `None` if the map has no
+    /// entry for it.
+    pub fn get<Q: ?Sized + Ord>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        self.get_key_value(key).map(|(_, v)| v)
+    }
+
+    /// Returns references to the stored key and the value for `key`, or `None`
+    /// if the map has no entry for it.
+    pub fn get_key_value<Q: ?Sized + Ord>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+    {
+        let i = self.search(key).ok()?;
+        let entry = &self.vec[i];
+        Some((&entry.0, &entry.1))
+    }
+
+    /// Returns a mutable reference to the value for `key`, or `None` if the
+    /// map has no entry for it.
+    pub fn get_mut<Q: ?Sized + Ord>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+    {
+        let i = self.search(key).ok()?;
+        Some(&mut self.vec[i].1)
+    }
+
+    /// Returns true if the map has an entry for `key`.
+    pub fn contains_key<Q: ?Sized + Ord>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.search(key).is_ok()
+    }
+
+    /// Inserts `value` for `key`. If the map already had an entry for `key`,
+    /// its value is replaced and the old value is returned as `Ok(Some(..))`;
+    /// the stored key is left as is. Otherwise a new entry is added and
+    /// `Ok(None)` is returned.
+    ///
+    /// # Error
+    ///
+    /// If a new entry is needed but the map is full, an `Err` is returned,
+    /// `key` and `value` are dropped and the map is left unchanged.
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, ()> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Ok(Some(entry.insert(value))),
+            Entry::Vacant(entry) => entry.insert(value).map(|_| None),
+        }
+    }
+
+    /// Removes the entry for `key` and returns its value, or returns `None` if
+    /// the map has no entry for it.
+    pub fn remove<Q: ?Sized + Ord>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    /// Removes the entry for `key` and returns the stored key and value, or
+    /// returns `None` if the map has no entry for it.
+    pub fn remove_entry<Q: ?Sized + Ord>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+    {
+        let i = self.search(key).ok()?;
+        Some(self.vec.remove(i))
+    }
+
+    /// Returns the entry for `key`, for in-place lookup and insertion.
+    pub fn entry(&mut self, key: K) -> Entry<'_, 'a, K, V> {
+        match self.search(&key) {
+            Ok(index) => Entry::Occupied(OccupiedEntry { map: self, index }),
+            Err(index) => Entry::Vacant(VacantEntry {
+                map: self,
+                key,
+                index,
+            }),
+        }
+    }
+
+    /// Retains only the entries for which `f` returns `true`.
+    pub fn retain<F: FnMut(&K, &V) -> bool>(&mut self, mut f: F) {
+        self.vec.retain(|entry| f(&entry.0, &entry.1))
+    }
+
+    /// Removes all entries from the map.
+    pub fn clear(&mut self) {
+        self.vec.clear()
+    }
+
+    /// Returns the entry with the smallest key, or `None` if the map is empty.
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.vec.first().map(|entry| (&entry.0, &entry.1))
+    }
+
+    /// Returns the entry with the largest key, or `None` if the map is empty.
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        self.vec.last().map(|entry| (&entry.0, &entry.1))
+    }
+
+    /// Returns an iterator over the entries, sorted by key.
+    pub fn iter(&self) -> MapIter<'_, K, V> {
+        MapIter {
+            inner: self.vec.iter(),
+        }
+    }
+
+    /// Returns an iterator over the entries, sorted by key, with mutable
+    /// references to the values.
+    pub fn iter_mut(&mut self) -> MapIterMut<'_, K, V> {
+        MapIterMut {
+            inner: self.vec.iter_mut(),
+        }
+    }
+
+    /// Returns an iterator over the keys, in order.
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> + ExactSizeIterator {
+        self.iter().map(|(k, _)| k)
+    }
+
+    /// Returns an iterator over the values, in key order.
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> + ExactSizeIterator {
+        self.iter().map(|(_, v)| v)
+    }
+
+    /// Returns an iterator over the entries whose keys lie in `range`, sorted
+    /// by key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than its end.
+    pub fn range<Q: ?Sized + Ord, R: RangeBounds<Q>>(&self, range: R) -> MapIter<'_, K, V>
+    where
+        K: Borrow<Q>,
+    {
+        let (start, end) = self.bounds(range);
+        MapIter {
+            inner: self.vec[start..end].iter(),
+        }
+    }
+
+    /// Returns an iterator over the entries whose keys lie in `range`, sorted
+    /// by key, with mutable references to the values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than its end.
+    pub fn range_mut<Q: ?Sized + Ord, R: RangeBounds<Q>>(
+        &mut self,
+        range: R,
+    ) -> MapIterMut<'_, K, V>
+    where
+        K: Borrow<Q>,
+    {
+        let (start, end) = self.bounds(range);
+        MapIterMut {
+            inner: self.vec[start..end].iter_mut(),
+        }
+    }
+
+    /// Returns the indices of the first entry in `range` and of the first
+    /// entry past it.
+    fn bounds<Q: ?Sized + Ord, R: RangeBounds<Q>>(&self, range: R) -> (usize, usize)
+    where
+        K: Borrow<Q>,
+    {
+        if let (Bound::Included(s), Bound::Included(e))
+        | (Bound::Included(s), Bound::Excluded(e))
+        | (Bound::Excluded(s), Bound::Included(e))
+        | (Bound::Excluded(s), Bound::Excluded(e)) = (range.start_bound(), range.end_bound())
+        {
+            assert!(s <= e, "range start is greater than its end");
+        }
+        let start = match range.start_bound() {
+            Bound::Included(q) => self.vec.partition_point(|e| e.0.borrow() < q),
+            Bound::Excluded(q) => self.vec.partition_point(|e| e.0.borrow() <= q),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(q) => self.vec.partition_point(|e| e.0.borrow() <= q),
+            Bound::Excluded(q) => self.vec.partition_point(|e| e.0.borrow() < q),
+            Bound::Unbounded => self.len(),
+        };
+        (start, end.max(start))
+    }
+}
+
+impl<'a, 'q, K, V, Q: ?Sized + Ord> Index<&'q Q> for StackMap<'a, K, V>
+where
+    K: Ord + Borrow<Q>,
+{
+    type Output = V;
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug> fmt::Debug for StackMap<'a, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.vec.iter().map(|entry| (&entry.0, &entry.1)))
+            .finish()
+    }
+}
+
+impl<'a, 'b, K: Ord, V> IntoIterator for &'a StackMap<'b, K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = MapIter<'a, K, V>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, 'b, K: Ord, V> IntoIterator for &'a mut StackMap<'b, K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = MapIterMut<'a, K, V>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// An iterator over the entries of a `StackMap`, sorted by key.
+///
+/// This `struct` is created by the `iter` and `range` methods on `StackMap`.
+pub struct MapIter<'b, K: 'b, V: 'b> {
+    inner: slice::Iter<'b, (K, V)>,
+}
+
+impl<'b, K: 'b, V: 'b> Iterator for MapIter<'b, K, V> {
+    type Item = (&'b K, &'b V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| (&entry.0, &entry.1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'b, K: 'b, V: 'b> DoubleEndedIterator for MapIter<'b, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|entry| (&entry.0, &entry.1))
+    }
+}
+
+impl<'b, K: 'b, V: 'b> ExactSizeIterator for MapIter<'b, K, V> {}
+
+/// An iterator over the entries of a `StackMap`, sorted by key, with mutable
+/// references to the values.
+///
+/// This `struct` is created by the `iter_mut` and `range_mut` methods on
+/// `StackMap`.
+pub struct MapIterMut<'b, K: 'b, V: 'b> {
+    inner: slice::IterMut<'b, (K, V)>,
+}
+
+impl<'b, K: 'b, V: 'b> Iterator for MapIterMut<'b, K, V> {
+    type Item = (&'b K, &'b mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| (&entry.0, &mut entry.1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'b, K: 'b, V: 'b> DoubleEndedIterator for MapIterMut<'b, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|entry| (&entry.0, &mut entry.1))
+    }
+}
+
+impl<'b, K: 'b, V: 'b> ExactSizeIterator for MapIterMut<'b, K, V> {}
+
+/// A view into a single entry of a `StackMap`, which may be either occupied or
+/// vacant.
+///
+/// This `enum` is created by the `entry` method on `StackMap`.
+pub enum Entry<'m, 'a: 'm, K: 'a, V: 'a> {
+    Occupied(OccupiedEntry<'m, 'a, K, V>),
+    Vacant(VacantEntry<'m, 'a, K, V>),
+}
+
+impl<'m, 'a: 'm, K: Ord + 'a, V: 'a> Entry<'m, 'a, K, V> {
+    /// Returns this entry's key.
+    pub fn key(&self) -> &K {
+        match *self {
+            Entry::Occupied(ref entry) => entry.key(),
+            Entry::Vacant(ref entry) => entry.key(),
+        }
+    }
+
+    /// Returns a mutable reference to the value of this entry, inserting
+    /// `default` first if the entry is vacant.
+    ///
+    /// # Error
+    ///
+    /// If the entry is vacant and the map is full, an `Err` is returned and
+    /// the map is left unchanged.
+    pub fn or_insert(self, default: V) -> Result<&'m mut V, ()> {
+        self.or_insert_with(|| default)
+    }
+
+    /// Returns a mutable reference to the value of this entry, inserting the
+    /// value returned by `default` first if the entry is vacant.
+    ///
+    /// # Error
+    ///
+    /// If the entry is vacant and the map is full, an `Err` is returned and
+    /// the map is left unchanged. `default` is not called in that case.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> Result<&'m mut V, ()> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                if entry.map.is_full() {
+                    return Err(());
+                }
+                entry.insert(default())
+            }
+        }
+    }
+
+    /// Calls `f` on the value of this entry if it is occupied.
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(ref mut entry) = self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'m, 'a: 'm, K: Ord + 'a, V: Default + 'a> Entry<'m, 'a, K, V> {
+    /// Returns a mutable reference to the value of this entry, inserting the
+    /// default value first if the entry is vacant.
+    ///
+    /// # Error
+    ///
+    /// If the entry is vacant and the map is full, an `Err` is returned and
+    /// the map is left unchanged.
+    pub fn or_default(self) -> Result<&'m mut V, ()> {
+        self.or_insert_with(V::default)
+    }
+}
+
+/// A view into an occupied entry of a `StackMap`.
+pub struct OccupiedEntry<'m, 'a: 'm, K: 'a, V: 'a> {
+    map: &'m mut StackMap<'a, K, V>,
+    index: usize,
+}
+
+impl<'m, 'a: 'm, K: Ord + 'a, V: 'a> OccupiedEntry<'m, 'a, K, V> {
+    /// Returns the key stored in the map for this entry.
+    pub fn key(&self) -> &K {
+        &self.map.vec[self.index].0
+    }
+
+    /// Returns a reference to the value of this entry.
+    pub fn get(&self) -> &V {
+        &self.map.vec[self.index].1
+    }
+
+    /// Returns a mutable reference to the value of this entry.
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.vec[self.index].1
+    }
+
+    /// Converts the entry into a mutable reference to its value that lives as
+    /// long as the borrow of the map.
+    pub fn into_mut(self) -> &'m mut V {
+        &mut self.map.vec[self.index].1
+    }
+
+    /// Replaces the value of this entry with `value` and returns the old
+    /// value.
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Removes this entry from the map and returns its value.
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    /// Removes this entry from the map and returns its key and value.
+    pub fn remove_entry(self) -> (K, V) {
+        self.map.vec.remove(self.index)
+    }
+}
+
+/// A view into a vacant entry of a `StackMap`.
+pub struct VacantEntry<'m, 'a: 'm, K: 'a, V: 'a> {
+    map: &'m mut StackMap<'a, K, V>,
+    key: K,
+    index: usize,
+}
+
+impl<'m, 'a: 'm, K: Ord + 'a, V: 'a> VacantEntry<'m, 'a, K, V> {
+    /// Returns the key that would be used when inserting into this entry.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Takes ownership of the key.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Inserts a new entry with this entry's key and `value`, and returns a
+    /// mutable reference to the value.
+    ///
+    /// # Error
+    ///
+    /// If the map is full, an `Err` is returned, the key and `value` are
+    /// dropped and the map is left unchanged.
+    pub fn insert(self, value: V) -> Result<&'m mut V, ()> {
+        let index = self.index;
+        self.map.vec.insert(index, (self.key, value))?;
+        Ok(&mut self.map.vec[index].1)
+    }
+}
@@ -0,0 +1,169 @@
+use core::marker::PhantomData;
+use core::ptr;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// A lock-free, single-producer/single-consumer FIFO queue backed by a slice.
+///
+/// `SpscDeque` is meant for handing data from an interrupt handler to the rest
+/// of the kernel, for instance bytes received by a UART. `split` hands out a
+/// `Producer` and a `Consumer` that may be used concurrently from different
+/// execution contexts without a lock. Neither `push` nor `pop` ever blocks or
+/// spins: both finish in a bounded number of steps and only use plain atomic
+/// loads and stores, so they're safe to call from an interrupt handler that
+/// preempts the other side.
+///
+/// Elements must be `Copy`, as values are handed across contexts by bitwise
+/// copy.
+pub struct SpscDeque<'a, T: Copy + 'a> {
+    buffer: *mut T,
+    capacity: usize,
+    /// Position of the next element to pop. Only written by the consumer.
+    head: AtomicUsize,
+    /// Position of the next slot to push into. Only written by the producer.
+    tail: AtomicUsize,
+    _storage: PhantomData<&'a mut [T]>,
+}
+
+// The producer and consumer only ever touch disjoint slots of the buffer, and
+// hand slots over to each other through `head` and `tail`.
+unsafe impl<'a, T: Copy + Send> Sync for SpscDeque<'a, T> {}
+unsafe impl<'a, T: Copy + Send> Send for SpscDeque<'a, T> {}
+
+impl<'a, T: Copy + 'a> SpscDeque<'a, T> {
+    /// Constructs a new, empty `SpscDeque<T>` using `storage` as the backing
+    /// store. The returned `SpscDeque` will be able to hold `storage.len()`
+    /// values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if twice `storage.len()` overflows a `usize`.
+    pub fn new(storage: &'a mut [T]) -> SpscDeque<'a, T> {
+        // Positions run from 0 to twice the capacity so that a full queue can
+        // be told apart from an empty one without giving up a slot.
+        assert!(storage.len().checked_mul(2).is_some());
+        SpscDeque {
+            buffer: storage.as_mut_ptr(),
+            capacity: storage.len(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            _storage: PhantomData,
+        }
+    }
+
+    /// Returns the number of elements this queue can hold.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of elements in the queue. If the queue is being
+    /// used concurrently, the value may be stale by the time it is returned.
+    pub fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        if tail >= head {
+            tail - head
+        } else {
+            tail + 2 * self.capacity - head
+        }
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if the queue is at capacity.
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity
+    }
+
+    /// Splits the queue into its producing and consuming halves.
+    pub fn split<'q>(&'q mut self) -> (Producer<'q, 'a, T>, Consumer<'q, 'a, T>) {
+        let queue: &'q SpscDeque<'a, T> = self;
+        (Producer { queue }, Consumer { queue })
+    }
+
+    /// Advances `pos` by one, wrapping at twice the capacity.
+    fn next(&self, pos: usize) -> usize {
+        if pos + 1 == 2 * self.capacity {
+            0
+        } else {
+            pos + 1
+        }
+    }
+
+    /// Returns a pointer to the slot for position `pos`.
+    fn slot(&self, pos: usize) -> *mut T {
+        let i = if pos >= self.capacity {
+            pos - self.capacity
+        } else {
+            pos
+        };
+        unsafe { self.buffer.add(i) }
+    }
+}
+
+/// The pushing half of an `SpscDeque`, returned by `SpscDeque::split`.
+pub struct Producer<'q, 'a: 'q, T: Copy + 'a> {
+    queue: &'q SpscDeque<'a, T>,
+}
+
+impl<'q, 'a: 'q, T: Copy + 'a> Producer<'q, 'a, T> {
+    /// Appends `value` to the back of the queue.
+    ///
+    /// # Error
+    ///
+    /// If the queue is full, an `Err` is returned and `value` is discarded.
+    /// Otherwise, `Ok` is returned.
+    pub fn push(&mut self, value: T) -> Result<(), ()> {
+        if self.queue.is_full() {
+            return Err(());
+        }
+        let tail = self.queue.tail.load(Ordering::Relaxed);
+        unsafe { ptr::write(self.queue.slot(tail), value) };
+        // Publish the slot only once it has been written.
+        self.queue
+            .tail
+            .store(self.queue.next(tail), Ordering::Release);
+        Ok(())
+    }
+
+    /// Returns true if the queue is at capacity.
+    pub fn is_full(&self) -> bool {
+        self.queue.is_full()
+    }
+}
+
+/// The popping half of an `SpscDeque`, returned by `SpscDeque::split`.
+pub struct Consumer<'q, 'a: 'q, T: Copy + 'a> {
+    queue: &'q SpscDeque<'a, T>,
+}
+
+impl<'q, 'a: 'q, T: Copy + 'a> Consumer<'q, 'a, T> {
+    /// Removes the front element of the queue and returns it, or `None` if
+    /// the queue is empty.
+    pub fn pop(&mut self) -> Option<T> {
+        let value = self.peek()?;
+        let head = self.queue.head.load(Ordering::Relaxed);
+        // Hand the slot back to the producer only once it has been read.
+        self.queue
+            .head
+            .store(self.queue.next(head), Ordering::Release);
+        Some(value)
+    }
+
+    /// Returns a copy of the front element of the queue without removing it,
+    /// or `None` if the queue is empty.
+    pub fn peek(&self) -> Option<T> {
+        if self.queue.is_empty() {
+            return None;
+        }
+        let head = self.queue.head.load(Ordering::Relaxed);
+        Some(unsafe { ptr::read(self.queue.slot(head)) })
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
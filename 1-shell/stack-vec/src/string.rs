@@ -0,0 +1,235 @@
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::str;
+
+use {rotate_left, rotate_right, StackVec};
+
+/// A UTF-8 encoded string backed by a byte slice.
+///
+/// `StackString` is to `String` what `StackVec` is to `Vec`: it requires no
+/// memory allocation, and its capacity in bytes is bounded by the
+/// user-supplied slice. Every operation that would grow the string past its
+/// capacity returns an `Err` and leaves the string unchanged, so a
+/// `StackString` always holds valid UTF-8.
+///
+/// Methods that take a position count in `char`s rather than in bytes, which
+/// is what a line editor's cursor tracks. `prev_char_boundary` and
+/// `next_char_boundary` move a byte offset by one character.
+pub struct StackString<'a> {
+    vec: StackVec<'a, u8>,
+}
+
+impl<'a> StackString<'a> {
+    /// Constructs a new, empty `StackString` using `storage` as the backing
+    /// store. The returned `StackString` will be able to hold `storage.len()`
+    /// bytes of UTF-8.
+    pub fn new(storage: &'a mut [u8]) -> StackString<'a> {
+        StackString {
+            vec: StackVec::new(storage),
+        }
+    }
+
+    /// Returns the number of bytes this string can hold.
+    pub fn capacity(&self) -> usize {
+        self.vec.capacity()
+    }
+
+    /// Returns the length of this string in bytes.
+    pub fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    /// Returns true if this string is empty.
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    /// Returns the number of `char`s in this string.
+    pub fn char_count(&self) -> usize {
+        self.as_str().chars().count()
+    }
+
+    /// Extracts a string slice containing the entire string.
+    pub fn as_str(&self) -> &str {
+        unsafe { str::from_utf8_unchecked(self.vec.as_slice()) }
+    }
+
+    /// Extracts a mutable string slice containing the entire string.
+    pub fn as_mut_str(&mut self) -> &mut str {
+        unsafe { str::from_utf8_unchecked_mut(self.vec.as_mut_slice()) }
+    }
+
+    /// Returns the string's bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.vec.as_slice()
+    }
+
+    /// Appends `ch` to the end of this string.
+    ///
+    /// # Error
+    ///
+    /// If there isn't room for the UTF-8 encoding of `ch`, an `Err` is
+    /// returned and the string is left unchanged. Otherwise, `Ok` is returned.
+    pub fn push(&mut self, ch: char) -> Result<(), ()> {
+        self.push_str(ch.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Appends `s` to the end of this string.
+    ///
+    /// # Error
+    ///
+    /// If there isn't room for all of `s`, an `Err` is returned and the string
+    /// is left unchanged. Otherwise, `Ok` is returned.
+    pub fn push_str(&mut self, s: &str) -> Result<(), ()> {
+        self.vec.extend_from_slice(s.as_bytes())
+    }
+
+    /// Removes the last `char` from this string and returns it, or returns
+    /// `None` if the string is empty.
+    pub fn pop(&mut self) -> Option<char> {
+        let ch = self.as_str().chars().next_back()?;
+        let len = self.len() - ch.len_utf8();
+        self.vec.truncate(len);
+        Some(ch)
+    }
+
+    /// Returns the byte offset of the `char` at position `char_idx`, or the
+    /// length of the string if `char_idx` is the number of `char`s in it.
+    /// Returns `None` if `char_idx` is past the end of the string.
+    pub fn byte_offset(&self, char_idx: usize) -> Option<usize> {
+        self.as_str()
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(Some(self.len()))
+            .nth(char_idx)
+    }
+
+    /// Inserts `ch` so that it becomes the `char` at position `char_idx`.
+    ///
+    /// # Error
+    ///
+    /// If there isn't room for the UTF-8 encoding of `ch`, an `Err` is
+    /// returned and the string is left unchanged. Otherwise, `Ok` is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is greater than the number of `char`s in the
+    /// string.
+    pub fn insert(&mut self, char_idx: usize, ch: char) -> Result<(), ()> {
+        self.insert_str(char_idx, ch.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Inserts `s` so that it starts at the `char` at position `char_idx`.
+    ///
+    /// # Error
+    ///
+    /// If there isn't room for all of `s`, an `Err` is returned and the string
+    /// is left unchanged. Otherwise, `Ok` is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is greater than the number of `char`s in the
+    /// string.
+    pub fn insert_str(&mut self, char_idx: usize, s: &str) -> Result<(), ()> {
+        let at = self
+            .byte_offset(char_idx)
+            .expect("insertion index is out of bounds");
+        self.push_str(s)?;
+        rotate_right(&mut self.vec.as_mut_slice()[at..], s.len());
+        Ok(())
+    }
+
+    /// Removes the `char` at position `char_idx` and returns it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_idx` is not less than the number of `char`s in the
+    /// string.
+    pub fn remove(&mut self, char_idx: usize) -> char {
+        let (at, ch) = self
+            .as_str()
+            .char_indices()
+            .nth(char_idx)
+            .expect("removal index is out of bounds");
+        rotate_left(&mut self.vec.as_mut_slice()[at..], ch.len_utf8());
+        let len = self.len() - ch.len_utf8();
+        self.vec.truncate(len);
+        ch
+    }
+
+    /// Returns the byte offset of the `char` boundary before `byte_idx`, or
+    /// `0` if `byte_idx` is at the start of the string. Offsets past the end
+    /// of the string are treated as the end of the string.
+    pub fn prev_char_boundary(&self, byte_idx: usize) -> usize {
+        let mut i = byte_idx.min(self.len());
+        while i > 0 {
+            i -= 1;
+            if self.as_str().is_char_boundary(i) {
+                break;
+            }
+        }
+        i
+    }
+
+    /// Returns the byte offset of the `char` boundary after `byte_idx`, or the
+    /// length of the string if `byte_idx` is at or past its end.
+    pub fn next_char_boundary(&self, byte_idx: usize) -> usize {
+        let mut i = byte_idx;
+        while i < self.len() {
+            i += 1;
+            if self.as_str().is_char_boundary(i) {
+                break;
+            }
+        }
+        i.min(self.len())
+    }
+
+    /// Shortens this string to `new_len` bytes. If `new_len` is greater than
+    /// the string's current length, this has no effect.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `new_len` doesn't lie on a `char` boundary.
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len < self.len() {
+            assert!(self.as_str().is_char_boundary(new_len));
+            self.vec.truncate(new_len);
+        }
+    }
+
+    /// Truncates this string, removing all contents.
+    pub fn clear(&mut self) {
+        self.vec.clear();
+    }
+}
+
+impl<'a> Deref for StackString<'a> {
+    type Target = str;
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<'a> DerefMut for StackString<'a> {
+    fn deref_mut(&mut self) -> &mut str {
+        self.as_mut_str()
+    }
+}
+
+impl<'a> fmt::Write for StackString<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s).map_err(|_| fmt::Error)
+    }
+}
+
+impl<'a> fmt::Display for StackString<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl<'a> fmt::Debug for StackString<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
@@ -1,5 +1,32 @@
+use core::cell::Cell;
+#[cfg(feature = "uninit")]
+use core::mem::MaybeUninit;
 use StackVec;
 
+/// Returns `N` uninitialized slots to back a collection with.
+#[cfg(feature = "uninit")]
+fn uninit<T, const N: usize>() -> [MaybeUninit<T>; N] {
+    unsafe { MaybeUninit::uninit().assume_init() }
+}
+
+/// Counts how many times values sharing `drops` have been dropped.
+#[derive(Debug, Clone)]
+struct Droppy<'a> {
+    id: usize,
+    drops: &'a Cell<usize>,
+}
+
+impl<'a> Drop for Droppy<'a> {
+    fn drop(&mut self) {
+        self.drops.set(self.drops.get() + 1);
+    }
+}
+
+/// Returns `n` `Droppy`s with ids `0..n` that count their drops in `drops`.
+fn droppies<'a>(n: usize, drops: &'a Cell<usize>) -> impl Iterator<Item = Droppy<'a>> {
+    (0..n).map(move |id| Droppy { id, drops })
+}
+
 #[test]
 fn assignment_text_example() {
     let mut storage = [0u8; 1024];
@@ -192,11 +219,114 @@ fn iterator() {
 
     let mut i = 0;
     for val in stack_vec {
-        assert_eq!(*val, i * i);
+        assert_eq!(val, i * i);
         i += 1;
     }
 }
 
+#[test]
+fn iterators_respect_len() {
+    let mut storage = [7usize; 16];
+    let mut stack_vec = StackVec::with_len(&mut storage, 2);
+
+    assert_eq!((&stack_vec).into_iter().count(), 2);
+    for val in &mut stack_vec {
+        *val += 1;
+    }
+    assert_eq!(stack_vec.as_slice(), &[8, 8]);
+
+    stack_vec.truncate(1);
+    assert_eq!((&stack_vec).into_iter().count(), 1);
+    assert_eq!(stack_vec.into_iter().count(), 1);
+}
+
+#[test]
+fn into_iter_by_value() {
+    let mut storage = [0usize; 4];
+    let mut stack_vec = StackVec::new(&mut storage);
+    for i in 1..4 {
+        stack_vec.push(i).expect("cap = 4");
+    }
+
+    let mut iter = stack_vec.into_iter();
+    assert_eq!(iter.len(), 3);
+    assert_eq!(iter.next(), Some(1));
+    assert_eq!(iter.next_back(), Some(3));
+    assert_eq!(iter.as_slice(), &[2]);
+    assert_eq!(iter.next(), Some(2));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.next_back(), None);
+}
+
+// Run under Miri to check that owned values are neither freed nor dropped
+// twice: elements are moved out, leaving default values in the storage.
+#[test]
+fn owned_values_are_dropped_once() {
+    use std::string::{String, ToString};
+
+    let mut storage: [String; 4] = Default::default();
+    {
+        let mut stack_vec = StackVec::new(&mut storage);
+        for s in &["a", "b", "c", "d"] {
+            stack_vec.push(s.to_string()).expect("cap = 4");
+        }
+        assert_eq!(stack_vec.pop().as_ref().map(|s| &s[..]), Some("d"));
+        assert_eq!(stack_vec.remove(0), "a");
+        assert_eq!(stack_vec.swap_remove(0), "b");
+        stack_vec.push(String::from("e")).expect("cap = 4");
+        assert!(stack_vec.drain(..1).eq(Some(String::from("c"))));
+        assert!(stack_vec.into_iter().eq(Some(String::from("e"))));
+    }
+    assert!(storage.iter().all(String::is_empty));
+
+    let drops = Cell::new(0);
+    {
+        let mut storage = [None, None, None];
+        let mut stack_vec = StackVec::new(&mut storage);
+        stack_vec.try_extend(droppies(3, &drops).map(Some)).expect("cap = 3");
+        assert_eq!(stack_vec.pop().map(|d| d.unwrap().id), Some(2));
+        assert_eq!(stack_vec.remove(0).map(|d| d.id), Some(0));
+        assert_eq!(stack_vec.drain(..).count(), 1);
+        assert_eq!(drops.get(), 3);
+    }
+    assert_eq!(drops.get(), 3);
+}
+
+#[test]
+fn drain() {
+    let mut storage = [0usize; 10];
+    let mut stack_vec = StackVec::new(&mut storage);
+    for i in 0..8 {
+        stack_vec.push(i).expect("cap = 10");
+    }
+
+    {
+        let mut drain = stack_vec.drain(2..5);
+        assert_eq!(drain.len(), 3);
+        assert_eq!(drain.next(), Some(2));
+        assert_eq!(drain.next_back(), Some(4));
+    }
+    assert_eq!(stack_vec.as_slice(), &[0, 1, 5, 6, 7]);
+
+    assert!(stack_vec.drain(..).eq([0, 1, 5, 6, 7].iter().cloned()));
+    assert!(stack_vec.is_empty());
+
+    stack_vec.push(1).expect("cap = 10");
+    stack_vec.push(2).expect("cap = 10");
+    assert_eq!(stack_vec.drain(1..2).next(), Some(2));
+    assert_eq!(stack_vec.drain(0..0).next(), None);
+    assert_eq!(stack_vec.as_slice(), &[1]);
+}
+
+#[test]
+#[should_panic]
+fn drain_out_of_bounds() {
+    let mut storage = [0usize; 10];
+    let mut stack_vec = StackVec::new(&mut storage);
+    stack_vec.push(1).expect("cap = 10");
+    stack_vec.drain(0..2);
+}
+
 #[test]
 fn as_slice() {
     let mut storage = [0usize; 5];
@@ -215,3 +345,881 @@ fn as_slice() {
     assert_eq!(stack_vec.as_slice(), &[102]);
     assert_eq!(stack_vec.as_mut_slice(), &mut [102]);
 }
+
+#[test]
+fn insert() {
+    let mut storage = [0usize; 4];
+    let mut stack_vec = StackVec::new(&mut storage);
+    stack_vec.insert(0, 2).expect("cap = 4");
+    stack_vec.insert(0, 0).expect("cap = 4");
+    stack_vec.insert(1, 1).expect("cap = 4");
+    stack_vec.insert(3, 3).expect("cap = 4");
+    assert_eq!(stack_vec.as_slice(), &[0, 1, 2, 3]);
+    assert!(stack_vec.insert(2, 9).is_err());
+    assert_eq!(stack_vec.as_slice(), &[0, 1, 2, 3]);
+}
+
+#[test]
+#[should_panic]
+fn insert_out_of_bounds() {
+    let mut storage = [0usize; 4];
+    let mut stack_vec = StackVec::new(&mut storage);
+    stack_vec.insert(1, 1).expect("cap = 4");
+}
+
+#[test]
+fn swap_remove() {
+    let mut storage = [0usize; 4];
+    let mut stack_vec = StackVec::new(&mut storage);
+    stack_vec.extend_from_slice(&[1, 2, 3, 4]).expect("cap = 4");
+    assert_eq!(stack_vec.swap_remove(0), 1);
+    assert_eq!(stack_vec.as_slice(), &[4, 2, 3]);
+    assert_eq!(stack_vec.swap_remove(2), 3);
+    assert_eq!(stack_vec.as_slice(), &[4, 2]);
+}
+
+#[test]
+#[should_panic(expected = "removal index is out of bounds")]
+fn swap_remove_from_empty() {
+    let mut storage = [0usize; 4];
+    StackVec::new(&mut storage).swap_remove(0);
+}
+
+#[test]
+fn retain() {
+    let mut storage = [0usize; 8];
+    let mut stack_vec = StackVec::new(&mut storage);
+    stack_vec.try_extend(0..8).expect("cap = 8");
+    stack_vec.retain(|&x| x % 3 != 0);
+    assert_eq!(stack_vec.as_slice(), &[1, 2, 4, 5, 7]);
+    stack_vec.retain(|_| false);
+    assert!(stack_vec.is_empty());
+}
+
+#[test]
+fn dedup() {
+    let mut storage = [0usize; 8];
+    let mut stack_vec = StackVec::new(&mut storage);
+    stack_vec
+        .extend_from_slice(&[1, 1, 2, 3, 3, 3, 1, 2])
+        .expect("cap = 8");
+    stack_vec.dedup();
+    assert_eq!(stack_vec.as_slice(), &[1, 2, 3, 1, 2]);
+
+    stack_vec.dedup_by_key(|x| *x / 2);
+    assert_eq!(stack_vec.as_slice(), &[1, 2, 1, 2]);
+}
+
+#[test]
+fn extend_fails_cleanly() {
+    let mut storage = [0usize; 4];
+    let mut stack_vec = StackVec::new(&mut storage);
+    stack_vec.extend_from_slice(&[1, 2]).expect("cap = 4");
+    assert!(stack_vec.extend_from_slice(&[3, 4, 5]).is_err());
+    assert_eq!(stack_vec.as_slice(), &[1, 2]);
+    assert!(stack_vec.try_extend(3..6).is_err());
+    assert_eq!(stack_vec.as_slice(), &[1, 2]);
+    stack_vec.try_extend(3..5).expect("cap = 4");
+    assert_eq!(stack_vec.as_slice(), &[1, 2, 3, 4]);
+}
+
+#[test]
+fn resize() {
+    let mut storage = [0usize; 4];
+    let mut stack_vec = StackVec::new(&mut storage);
+    stack_vec.resize(3, 7).expect("cap = 4");
+    assert_eq!(stack_vec.as_slice(), &[7, 7, 7]);
+    stack_vec.resize(1, 0).expect("cap = 4");
+    assert_eq!(stack_vec.as_slice(), &[7]);
+    assert!(stack_vec.resize(5, 0).is_err());
+    assert_eq!(stack_vec.as_slice(), &[7]);
+}
+
+#[test]
+fn split_off() {
+    let mut storage = [0usize; 6];
+    let mut stack_vec = StackVec::new(&mut storage);
+    stack_vec.try_extend(0..6).expect("cap = 6");
+
+    let mut small = [0usize; 2];
+    assert!(stack_vec.split_off(3, &mut small).is_err());
+    assert_eq!(stack_vec.len(), 6);
+
+    let mut other_storage = [0usize; 3];
+    {
+        let other = stack_vec.split_off(3, &mut other_storage).expect("fits");
+        assert_eq!(other.as_slice(), &[3, 4, 5]);
+        assert_eq!(other.capacity(), 3);
+    }
+    assert_eq!(stack_vec.as_slice(), &[0, 1, 2]);
+}
+
+#[test]
+fn clear() {
+    let mut storage = [0usize; 4];
+    let mut stack_vec = StackVec::new(&mut storage);
+    stack_vec.extend_from_slice(&[1, 2, 3]).expect("cap = 4");
+    stack_vec.clear();
+    assert!(stack_vec.is_empty());
+    assert_eq!(stack_vec.capacity(), 4);
+}
+
+#[cfg(feature = "uninit")]
+mod uninit {
+    use core::cell::Cell;
+    use super::{droppies, uninit, Droppy};
+    use UninitStackVec;
+
+    #[test]
+    fn push_pop() {
+        let mut storage = uninit::<_, 8>();
+        let mut vec = UninitStackVec::new(&mut storage);
+        assert!(vec.is_empty());
+        assert_eq!(vec.capacity(), 8);
+        for i in 0..8 {
+            vec.push(i).expect("cap = 8");
+        }
+        assert!(vec.is_full());
+        assert!(vec.push(8).is_err());
+        assert_eq!(vec.as_slice(), &[0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(vec.pop(), Some(7));
+        assert_eq!(vec.remove(0), 0);
+        assert_eq!(vec.swap_remove(0), 1);
+        vec.insert(1, 9).expect("cap = 8");
+        assert_eq!(vec.as_slice(), &[6, 9, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "removal index is out of bounds")]
+    fn swap_remove_from_empty() {
+        let mut storage = uninit::<usize, 4>();
+        UninitStackVec::new(&mut storage).swap_remove(0);
+    }
+
+    #[test]
+    fn drops_on_truncate_clear_and_drop() {
+        let drops = Cell::new(0);
+        let mut storage = uninit::<_, 8>();
+        {
+            let mut vec = UninitStackVec::new(&mut storage);
+            vec.try_extend(droppies(6, &drops)).expect("cap = 8");
+            vec.truncate(4);
+            assert_eq!(drops.get(), 2);
+            vec.truncate(10);
+            assert_eq!(drops.get(), 2);
+
+            vec.clear();
+            assert_eq!(drops.get(), 6);
+
+            vec.try_extend(droppies(3, &drops)).expect("cap = 8");
+        }
+        assert_eq!(drops.get(), 9);
+    }
+
+    #[test]
+    fn moved_out_values_are_dropped_once() {
+        let drops = Cell::new(0);
+        let mut storage = uninit::<_, 8>();
+        {
+            let mut vec = UninitStackVec::new(&mut storage);
+            vec.try_extend(droppies(5, &drops)).expect("cap = 8");
+            assert_eq!(vec.pop().map(|d| d.id), Some(4));
+            assert_eq!(vec.remove(0).id, 0);
+            assert_eq!(vec.swap_remove(0).id, 1);
+            assert_eq!(drops.get(), 3);
+            vec.try_extend(droppies(2, &drops)).expect("cap = 8");
+        }
+        assert_eq!(drops.get(), 7);
+    }
+
+    #[test]
+    fn failed_push_drops_value() {
+        let drops = Cell::new(0);
+        let mut storage = uninit::<_, 8>();
+        let mut vec = UninitStackVec::new(&mut storage);
+        vec.try_extend(droppies(8, &drops)).expect("cap = 8");
+        let extra = Droppy {
+            id: 8,
+            drops: &drops,
+        };
+        assert!(vec.push(extra).is_err());
+        assert_eq!(drops.get(), 1);
+
+        vec.truncate(6);
+        assert_eq!(drops.get(), 3);
+        assert!(vec.try_extend(droppies(3, &drops)).is_err());
+        assert_eq!(drops.get(), 6);
+        assert_eq!(vec.len(), 6);
+    }
+
+    #[test]
+    fn retain_drops_removed() {
+        let drops = Cell::new(0);
+        let mut storage = uninit::<_, 8>();
+        {
+            let mut vec = UninitStackVec::new(&mut storage);
+            vec.try_extend(droppies(8, &drops)).expect("cap = 8");
+            vec.retain(|d| d.id % 2 == 0);
+            assert_eq!(drops.get(), 4);
+            let ids: [usize; 4] = [vec[0].id, vec[1].id, vec[2].id, vec[3].id];
+            assert_eq!(ids, [0, 2, 4, 6]);
+        }
+        assert_eq!(drops.get(), 8);
+    }
+
+    #[test]
+    fn into_iter_drops_remaining() {
+        let drops = Cell::new(0);
+        let mut storage = uninit::<_, 8>();
+        let mut vec = UninitStackVec::new(&mut storage);
+        vec.try_extend(droppies(5, &drops)).expect("cap = 8");
+        {
+            let mut iter = vec.into_iter();
+            assert_eq!(iter.len(), 5);
+            assert_eq!(iter.next().map(|d| d.id), Some(0));
+            assert_eq!(iter.next_back().map(|d| d.id), Some(4));
+            assert_eq!(drops.get(), 2);
+        }
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    fn references_iterate_over_len() {
+        let mut storage = uninit::<_, 8>();
+        let mut vec = UninitStackVec::new(&mut storage);
+        vec.extend_from_slice(&[1usize, 2, 3]).expect("cap = 8");
+        for val in &mut vec {
+            *val *= 2;
+        }
+        assert!((&vec).into_iter().eq([2, 4, 6].iter()));
+    }
+}
+
+mod string {
+    use core::fmt::Write;
+    use StackString;
+
+    #[test]
+    fn push_and_capacity() {
+        let mut storage = [0u8; 4];
+        let mut s = StackString::new(&mut storage);
+        assert!(s.is_empty());
+        s.push('a').expect("cap = 4");
+        s.push('é').expect("cap = 4");
+        assert_eq!(s.len(), 3);
+        assert_eq!(s.char_count(), 2);
+        assert!(s.push('€').is_err());
+        assert!(s.push_str("bc").is_err());
+        assert_eq!(&*s, "aé");
+        s.push_str("b").expect("cap = 4");
+        assert_eq!(s.as_str(), "aéb");
+        assert!(s.push('b').is_err());
+    }
+
+    #[test]
+    fn insert_and_remove_by_char() {
+        let mut storage = [0u8; 16];
+        let mut s = StackString::new(&mut storage);
+        s.push_str("hé€o").expect("cap = 16");
+        s.insert(3, 'l').expect("cap = 16");
+        s.insert(0, '¡').expect("cap = 16");
+        s.insert_str(6, "!!").expect("cap = 16");
+        assert_eq!(s.as_str(), "¡hé€lo!!");
+
+        assert_eq!(s.remove(3), '€');
+        assert_eq!(s.remove(0), '¡');
+        assert_eq!(s.as_str(), "hélo!!");
+        assert_eq!(s.pop(), Some('!'));
+        assert_eq!(s.as_str(), "hélo!");
+    }
+
+    #[test]
+    fn insert_fails_cleanly() {
+        let mut storage = [0u8; 4];
+        let mut s = StackString::new(&mut storage);
+        s.push_str("abc").expect("cap = 4");
+        assert!(s.insert(1, 'é').is_err());
+        assert_eq!(s.as_str(), "abc");
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_out_of_bounds() {
+        let mut storage = [0u8; 8];
+        let mut s = StackString::new(&mut storage);
+        s.push_str("é").expect("cap = 8");
+        let _ = s.insert(2, 'a');
+    }
+
+    #[test]
+    fn char_boundaries() {
+        let mut storage = [0u8; 16];
+        let mut s = StackString::new(&mut storage);
+        s.push_str("aé€").expect("cap = 16");
+        assert_eq!(s.byte_offset(0), Some(0));
+        assert_eq!(s.byte_offset(2), Some(3));
+        assert_eq!(s.byte_offset(3), Some(6));
+        assert_eq!(s.byte_offset(4), None);
+
+        assert_eq!(s.next_char_boundary(0), 1);
+        assert_eq!(s.next_char_boundary(1), 3);
+        assert_eq!(s.next_char_boundary(3), 6);
+        assert_eq!(s.next_char_boundary(6), 6);
+        assert_eq!(s.prev_char_boundary(6), 3);
+        assert_eq!(s.prev_char_boundary(3), 1);
+        assert_eq!(s.prev_char_boundary(1), 0);
+        assert_eq!(s.prev_char_boundary(0), 0);
+        assert_eq!(s.prev_char_boundary(100), 3);
+    }
+
+    #[test]
+    fn formatting() {
+        let mut storage = [0u8; 8];
+        let mut s = StackString::new(&mut storage);
+        write!(s, "{}-{}", 12, "ab").expect("cap = 8");
+        assert_eq!(s.as_str(), "12-ab");
+        assert!(write!(s, "{}", 1234).is_err());
+
+        let mut other_storage = [0u8; 8];
+        let mut other = StackString::new(&mut other_storage);
+        write!(other, "{}", s).expect("cap = 8");
+        assert_eq!(other.as_str(), "12-ab");
+
+        s.clear();
+        assert!(s.is_empty());
+    }
+}
+
+#[cfg(feature = "uninit")]
+mod deque {
+    use core::cell::Cell;
+    use super::{droppies, uninit};
+    use StackDeque;
+
+    #[test]
+    fn fifo() {
+        let mut storage = uninit::<usize, 4>();
+        let mut deque = StackDeque::new(&mut storage);
+        assert!(deque.is_empty());
+        assert_eq!(deque.pop_front(), None);
+
+        for round in 0..3 {
+            for i in 0..4 {
+                deque.push_back(round * 10 + i).expect("cap = 4");
+            }
+            assert!(deque.is_full());
+            assert!(deque.push_back(99).is_err());
+            assert!(deque.push_front(99).is_err());
+            for i in 0..3 {
+                assert_eq!(deque.pop_front(), Some(round * 10 + i));
+            }
+            assert_eq!(deque.pop_back(), Some(round * 10 + 3));
+            assert!(deque.is_empty());
+            // Move the head around so later rounds wrap.
+            deque.push_back(0).expect("cap = 4");
+            deque.pop_front();
+        }
+    }
+
+    #[test]
+    fn both_ends() {
+        let mut storage = uninit::<usize, 4>();
+        let mut deque = StackDeque::new(&mut storage);
+        deque.push_back(2).expect("cap = 4");
+        deque.push_front(1).expect("cap = 4");
+        deque.push_back(3).expect("cap = 4");
+        deque.push_front(0).expect("cap = 4");
+        assert_eq!(deque.front(), Some(&0));
+        assert_eq!(deque.back(), Some(&3));
+        assert!(deque.iter().eq([0, 1, 2, 3].iter()));
+        assert_eq!(deque.pop_back(), Some(3));
+        assert_eq!(deque.pop_front(), Some(0));
+        assert_eq!(deque.as_slices().0.len() + deque.as_slices().1.len(), 2);
+    }
+
+    #[test]
+    fn overwriting() {
+        let mut storage = uninit::<usize, 3>();
+        let mut deque = StackDeque::overwriting(&mut storage);
+        assert!(deque.is_overwriting());
+        for i in 0..5 {
+            deque.push_back(i).expect("overwriting");
+        }
+        assert_eq!(deque.len(), 3);
+        assert!(deque.iter().eq([2, 3, 4].iter()));
+
+        deque.push_front(9).expect("overwriting");
+        assert!(deque.iter().eq([9, 2, 3].iter()));
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let mut storage = uninit::<usize, 0>();
+        let mut deque = StackDeque::overwriting(&mut storage);
+        assert!(deque.push_back(1).is_err());
+        assert!(deque.push_front(1).is_err());
+        assert_eq!(deque.pop_front(), None);
+    }
+
+    #[test]
+    fn indexing_and_iteration() {
+        let mut storage = uninit::<usize, 4>();
+        let mut deque = StackDeque::new(&mut storage);
+        deque.push_back(0).expect("cap = 4");
+        deque.push_back(0).expect("cap = 4");
+        deque.pop_front();
+        deque.pop_front();
+        for i in 0..4 {
+            deque.push_back(i).expect("cap = 4");
+        }
+        let (front, back) = deque.as_slices();
+        assert_eq!(front, &[0, 1]);
+        assert_eq!(back, &[2, 3]);
+
+        for i in 0..4 {
+            assert_eq!(deque[i], i);
+        }
+        assert_eq!(deque.get(4), None);
+
+        for val in &mut deque {
+            *val *= 10;
+        }
+        deque[0] = 5;
+        assert!((&deque).into_iter().eq([5, 10, 20, 30].iter()));
+
+        deque.clear();
+        assert!(deque.is_empty());
+        assert_eq!(deque.iter().next(), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_oob() {
+        let mut storage = uninit::<usize, 4>();
+        let mut deque = StackDeque::new(&mut storage);
+        deque.push_back(1).expect("cap = 4");
+        let _ = deque[1];
+    }
+
+    #[test]
+    fn drops_elements_once() {
+        let drops = Cell::new(0);
+        let mut storage = uninit::<_, 3>();
+        {
+            let mut deque = StackDeque::overwriting(&mut storage);
+            for d in droppies(5, &drops) {
+                deque.push_back(d).expect("overwriting");
+            }
+            assert_eq!(drops.get(), 2);
+            assert_eq!(deque.pop_front().map(|d| d.id), Some(2));
+            assert_eq!(drops.get(), 3);
+            for d in droppies(2, &drops) {
+                deque.push_front(d).expect("overwriting");
+            }
+            assert_eq!(drops.get(), 4);
+            assert!(deque.iter().map(|d| d.id).eq([1, 0, 3].iter().cloned()));
+            deque.clear();
+            assert_eq!(drops.get(), 7);
+            for d in droppies(2, &drops) {
+                deque.push_back(d).expect("overwriting");
+            }
+        }
+        assert_eq!(drops.get(), 9);
+    }
+}
+
+#[cfg(feature = "uninit")]
+mod map {
+    use core::ops::Bound;
+    use super::uninit;
+    use Entry;
+    use StackMap;
+
+    #[test]
+    fn insert_get_remove() {
+        let mut storage = uninit::<_, 4>();
+        let mut map = StackMap::new(&mut storage);
+        assert!(map.is_empty());
+        assert_eq!(map.insert("path", 1), Ok(None));
+        assert_eq!(map.insert("home", 2), Ok(None));
+        assert_eq!(map.insert("user", 3), Ok(None));
+        assert_eq!(map.insert("home", 4), Ok(Some(2)));
+        assert_eq!(map.len(), 3);
+
+        assert_eq!(map.get("home"), Some(&4));
+        assert_eq!(map["user"], 3);
+        assert_eq!(map.get("shell"), None);
+        assert!(map.contains_key("path"));
+        *map.get_mut("path").unwrap() += 10;
+        assert_eq!(map.get_key_value("path"), Some((&"path", &11)));
+
+        assert_eq!(map.remove("home"), Some(4));
+        assert_eq!(map.remove("home"), None);
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn overflow() {
+        let mut storage = uninit::<_, 4>();
+        let mut map = StackMap::new(&mut storage);
+        for (i, key) in ["d", "c", "b", "a"].iter().enumerate() {
+            assert_eq!(map.insert(*key, i), Ok(None));
+        }
+        assert!(map.is_full());
+        assert_eq!(map.insert("e", 9), Err(()));
+        assert_eq!(map.insert("a", 9), Ok(Some(3)));
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.get("e"), None);
+    }
+
+    #[test]
+    fn sorted_iteration() {
+        let mut storage = uninit::<_, 4>();
+        let mut map = StackMap::new(&mut storage);
+        for &(key, value) in &[("c", 3), ("a", 1), ("d", 4), ("b", 2)] {
+            map.insert(key, value).unwrap();
+        }
+        let mut expected = ["a", "b", "c", "d"].iter();
+        for (key, value) in &map {
+            assert_eq!(key, expected.next().unwrap());
+            assert_eq!(map[*key], *value);
+        }
+        assert_eq!(map.keys().rev().next(), Some(&"d"));
+        assert_eq!(map.values().sum::<usize>(), 10);
+        assert_eq!(map.first_key_value(), Some((&"a", &1)));
+        assert_eq!(map.last_key_value(), Some((&"d", &4)));
+
+        for (_, value) in &mut map {
+            *value *= 10;
+        }
+        assert_eq!(map["c"], 30);
+    }
+
+    #[test]
+    fn range() {
+        let mut storage = uninit::<_, 8>();
+        let mut map = StackMap::new(&mut storage);
+        for key in &[5, 1, 7, 3] {
+            map.insert(*key, key * key).unwrap();
+        }
+        let keys = |iter: ::MapIter<usize, usize>| iter.map(|(k, _)| *k).sum::<usize>();
+        assert_eq!(keys(map.range(2..7)), 3 + 5);
+        assert_eq!(keys(map.range(3..=7)), 3 + 5 + 7);
+        assert_eq!(keys(map.range(..3)), 1);
+        assert_eq!(keys(map.range(4..)), 5 + 7);
+        assert_eq!(keys(map.range(8..)), 0);
+        assert_eq!(map.range(..).len(), 4);
+
+        for (_, value) in map.range_mut(5..) {
+            *value = 0;
+        }
+        assert_eq!(map[&7], 0);
+        assert_eq!(map[&3], 9);
+    }
+
+    #[test]
+    #[should_panic]
+    fn range_backwards() {
+        let mut storage = uninit::<_, 2>();
+        let map = StackMap::<usize, usize>::new(&mut storage);
+        map.range((Bound::Included(2), Bound::Excluded(1)));
+    }
+
+    #[test]
+    fn entry() {
+        let mut storage = uninit::<_, 4>();
+        let mut map = StackMap::new(&mut storage);
+        *map.entry("ls").or_insert(0).unwrap() += 1;
+        *map.entry("ls").or_insert(0).unwrap() += 1;
+        assert_eq!(map["ls"], 2);
+
+        map.entry("ls").and_modify(|v| *v = 7).or_default().unwrap();
+        map.entry("cd").and_modify(|v| *v = 7).or_default().unwrap();
+        assert_eq!(map["ls"], 7);
+        assert_eq!(map["cd"], 0);
+
+        match map.entry("cd") {
+            Entry::Occupied(entry) => assert_eq!(entry.remove_entry(), ("cd", 0)),
+            Entry::Vacant(_) => panic!("expected occupied entry"),
+        }
+        match map.entry("pwd") {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), &"pwd");
+                *entry.insert(1).unwrap() += 1;
+            }
+            Entry::Occupied(_) => panic!("expected vacant entry"),
+        }
+        assert_eq!(map["pwd"], 2);
+
+        map.insert("a", 0).unwrap();
+        map.insert("b", 0).unwrap();
+        assert!(map.is_full());
+        let mut called = false;
+        assert!(map
+            .entry("z")
+            .or_insert_with(|| {
+                called = true;
+                0
+            })
+            .is_err());
+        assert!(!called);
+        assert_eq!(map.len(), 4);
+    }
+
+    #[test]
+    fn retain_and_clear() {
+        let mut storage = uninit::<_, 4>();
+        let mut map = StackMap::new(&mut storage);
+        for &(key, value) in &[("a", 1), ("b", 2), ("c", 3)] {
+            map.insert(key, value).unwrap();
+        }
+        map.retain(|_, v| v % 2 == 1);
+        assert_eq!(
+            map.keys().cloned().collect::<::std::vec::Vec<_>>(),
+            ["a", "c"]
+        );
+        map.clear();
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn owned_entries() {
+        use std::string::String;
+
+        let mut storage = uninit::<_, 4>();
+        let mut map = StackMap::new(&mut storage);
+        for &key in &["c", "a", "b"] {
+            map.insert(String::from(key), String::from(key)).unwrap();
+        }
+        assert_eq!(map.remove("a").as_ref().map(|s| &s[..]), Some("a"));
+        let (key, value) = match map.entry(String::from("b")) {
+            Entry::Occupied(entry) => entry.remove_entry(),
+            Entry::Vacant(_) => panic!("expected an occupied entry"),
+        };
+        assert_eq!((&key[..], &value[..]), ("b", "b"));
+        assert_eq!(map.get("c").map(|s| &s[..]), Some("c"));
+        assert_eq!(map.len(), 1);
+    }
+}
+
+mod spsc {
+    use std::thread;
+    use SpscDeque;
+
+    #[test]
+    fn push_pop() {
+        let mut storage = [0u8; 3];
+        let mut queue = SpscDeque::new(&mut storage);
+        {
+            let (mut producer, mut consumer) = queue.split();
+            assert!(consumer.is_empty());
+            assert_eq!(consumer.pop(), None);
+            for round in 0..4 {
+                for i in 0..3 {
+                    producer.push(round + i).expect("cap = 3");
+                }
+                assert!(producer.is_full());
+                assert!(producer.push(9).is_err());
+                assert_eq!(consumer.peek(), Some(round));
+                for i in 0..3 {
+                    assert_eq!(consumer.pop(), Some(round + i));
+                }
+                assert_eq!(consumer.pop(), None);
+            }
+        }
+        assert!(queue.is_empty());
+        assert_eq!(queue.capacity(), 3);
+    }
+
+    #[test]
+    fn concurrent() {
+        const COUNT: usize = 1000;
+
+        let mut storage = [0usize; 8];
+        let mut queue = SpscDeque::new(&mut storage);
+        let (mut producer, mut consumer) = queue.split();
+
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                for i in 0..COUNT {
+                    while producer.push(i).is_err() {
+                        thread::yield_now();
+                    }
+                }
+            });
+
+            let mut expected = 0;
+            while expected < COUNT {
+                match consumer.pop() {
+                    Some(value) => {
+                        assert_eq!(value, expected);
+                        expected += 1;
+                    }
+                    None => thread::yield_now(),
+                }
+            }
+        });
+        assert!(consumer.is_empty());
+    }
+}
+
+#[cfg(feature = "uninit")]
+mod array {
+    use core::cell::Cell;
+    use core::convert::TryFrom;
+    use super::{droppies, Droppy};
+    use ArrayVec;
+    use StackVec;
+
+    fn make() -> ArrayVec<usize, 8> {
+        let mut vec = ArrayVec::new();
+        vec.try_extend(0..5).expect("cap = 8");
+        vec
+    }
+
+    #[test]
+    fn returned_by_value() {
+        let mut vec = make();
+        assert_eq!(vec.capacity(), 8);
+        assert_eq!(&*vec, &[0, 1, 2, 3, 4]);
+        assert!(vec.push(5).is_ok());
+        assert_eq!(vec.pop(), Some(5));
+        assert_eq!(vec.remove(0), 0);
+        assert!(vec.insert(1, 9).is_ok());
+        assert_eq!(&*vec, &[1, 9, 2, 3, 4]);
+        assert_eq!(vec.swap_remove(0), 1);
+        assert_eq!(&*vec, &[4, 9, 2, 3]);
+        vec.retain(|&x| x != 9);
+        assert_eq!(&*vec, &[4, 2, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "removal index is out of bounds")]
+    fn swap_remove_from_empty() {
+        ArrayVec::<usize, 4>::new().swap_remove(0);
+    }
+
+    #[test]
+    fn full() {
+        let mut vec = ArrayVec::<u8, 2>::new();
+        assert!(vec.push(1).is_ok());
+        assert!(vec.push(2).is_ok());
+        assert!(vec.is_full());
+        assert!(vec.push(3).is_err());
+        assert!(vec.insert(0, 3).is_err());
+        assert!(vec.extend_from_slice(&[3]).is_err());
+        assert!(vec.try_extend(0..1).is_err());
+        assert_eq!(&*vec, &[1, 2]);
+        assert!(vec.resize(3, 0).is_err());
+    }
+
+    #[test]
+    fn drops_elements() {
+        let drops = Cell::new(0);
+        {
+            let mut vec = ArrayVec::<Droppy, 4>::new();
+            vec.try_extend(droppies(4, &drops)).expect("cap = 4");
+            assert!(vec.push(Droppy { id: 4, drops: &drops }).is_err());
+            assert_eq!(drops.get(), 1);
+            vec.truncate(3);
+            assert_eq!(drops.get(), 2);
+            drop(vec.pop());
+            assert_eq!(drops.get(), 3);
+        }
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    fn into_iter_drops_rest() {
+        let drops = Cell::new(0);
+        let mut vec = ArrayVec::<Droppy, 4>::new();
+        vec.try_extend(droppies(4, &drops)).expect("cap = 4");
+        let mut iter = vec.into_iter();
+        assert_eq!(iter.len(), 4);
+        drop(iter.next());
+        drop(iter.next_back());
+        assert_eq!(drops.get(), 2);
+        drop(iter);
+        assert_eq!(drops.get(), 4);
+
+        let sum: usize = make().into_iter().sum();
+        assert_eq!(sum, 10);
+    }
+
+    #[test]
+    fn drain() {
+        let mut vec = make();
+        let mut drain = vec.drain(1..3);
+        assert_eq!(drain.len(), 2);
+        assert_eq!(drain.next_back(), Some(2));
+        assert_eq!(drain.next(), Some(1));
+        assert_eq!(drain.next(), None);
+        drop(drain);
+        assert_eq!(&*vec, &[0, 3, 4]);
+        vec.drain(..1);
+        assert_eq!(&*vec, &[3, 4]);
+        vec.drain(..);
+        assert!(vec.is_empty());
+    }
+
+    #[test]
+    fn split_off_and_dedup() {
+        let mut vec = ArrayVec::from([1, 1, 2, 2, 2, 3]);
+        assert!(vec.is_full());
+        let tail = vec.split_off(3);
+        assert_eq!(&*vec, &[1, 1, 2]);
+        assert_eq!(&*tail, &[2, 2, 3]);
+        vec.dedup();
+        assert_eq!(&*vec, &[1, 2]);
+        assert_eq!(vec.clone(), vec);
+    }
+
+    #[test]
+    fn stack_vec_conversions() {
+        let mut storage = [0usize; 8];
+        let mut stack = StackVec::new(&mut storage);
+        stack.push(7).unwrap();
+        stack.push(8).unwrap();
+
+        let array = ArrayVec::<usize, 4>::try_from(&stack[..]).unwrap();
+        assert_eq!(&*array, &[7, 8]);
+        assert!(ArrayVec::<usize, 1>::try_from(&stack[..]).is_err());
+
+        let stack = ArrayVec::<usize, 1>::try_from(stack).unwrap_err();
+        let array = ArrayVec::<usize, 2>::try_from(stack).unwrap();
+        assert_eq!(&*array, &[7, 8]);
+
+        let array = array.into_stack_vec(&mut [0; 1]).unwrap_err();
+        let mut storage = [0usize; 2];
+        let stack = array.into_stack_vec(&mut storage).unwrap();
+        assert_eq!(&*stack, &[7, 8]);
+        assert!(stack.is_full());
+    }
+
+    #[test]
+    fn stack_vec_conversions_drop_once() {
+        let drops = Cell::new(0);
+        let mut storage = [
+            Droppy { id: 7, drops: &drops },
+            Droppy { id: 8, drops: &drops },
+            Droppy { id: 9, drops: &drops },
+        ];
+        {
+            let stack = StackVec::with_len(&mut storage, 2);
+            let array = ArrayVec::<Droppy, 2>::try_from(stack).expect("cap = 2");
+            assert_eq!(array[1].id, 8);
+            assert_eq!(drops.get(), 0);
+        }
+        assert_eq!(drops.get(), 2);
+
+        let mut array = ArrayVec::<Droppy, 2>::new();
+        array.try_extend(droppies(2, &drops)).expect("cap = 2");
+        {
+            let stack = array.into_stack_vec(&mut storage).expect("cap = 3");
+            assert_eq!(stack.len(), 2);
+            assert_eq!(drops.get(), 4);
+        }
+        let ids: [usize; 3] = [storage[0].id, storage[1].id, storage[2].id];
+        assert_eq!(ids, [0, 1, 9]);
+    }
+}
@@ -0,0 +1,311 @@
+use core::fmt;
+use core::iter::{DoubleEndedIterator, ExactSizeIterator, IntoIterator, Iterator};
+use core::mem::{ManuallyDrop, MaybeUninit};
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+use core::slice::{self, Iter, IterMut};
+
+/// A contiguous array type backed by a slice of possibly uninitialized memory.
+///
+/// `UninitStackVec` offers the same interface as `StackVec`, but its backing
+/// slice doesn't need to be initialized up front. Only the first `len` slots
+/// ever hold live values: elements are dropped when they're removed by
+/// `truncate` or `clear` and when the vector itself is dropped, and values
+/// moved out of the vector leave nothing behind. This makes `UninitStackVec`
+/// suitable for element types that implement `Drop` or that have no cheap
+/// placeholder value to fill a `StackVec`'s storage with.
+pub struct UninitStackVec<'a, T: 'a> {
+    storage: &'a mut [MaybeUninit<T>],
+    len: usize,
+}
+
+impl<'a, T: 'a> UninitStackVec<'a, T> {
+    /// Constructs a new, empty `UninitStackVec<T>` using `storage` as the
+    /// backing store. The returned `UninitStackVec` will be able to hold
+    /// `storage.len()` values. Any values already in `storage` are ignored.
+    pub fn new(storage: &'a mut [MaybeUninit<T>]) -> UninitStackVec<'a, T> {
+        UninitStackVec { storage, len: 0 }
+    }
+
+    /// Returns the number of elements this vector can hold.
+    pub fn capacity(&self) -> usize {
+        self.storage.len()
+    }
+
+    /// Returns the number of elements in the vector, also referred to as its
+    /// 'length'.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the vector contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns true if the vector is at capacity.
+    pub fn is_full(&self) -> bool {
+        self.len == self.storage.len()
+    }
+
+    /// Extracts a slice containing the entire vector.
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.storage.as_ptr() as *const T, self.len) }
+    }
+
+    /// Extracts a mutable slice of the entire vector.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.storage.as_mut_ptr() as *mut T, self.len) }
+    }
+
+    /// Appends `value` to the back of this vector if the vector is not full.
+    ///
+    /// # Error
+    ///
+    /// If this vector is full, an `Err` is returned and `value` is dropped.
+    /// Otherwise, `Ok` is returned.
+    pub fn push(&mut self, value: T) -> Result<(), ()> {
+        if self.is_full() {
+            Err(())
+        } else {
+            self.storage[self.len] = MaybeUninit::new(value);
+            self.len += 1;
+            Ok(())
+        }
+    }
+
+    /// If this vector is not empty, removes the last element from this vector
+    /// and returns it. Otherwise returns `None`.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            None
+        } else {
+            self.len -= 1;
+            Some(unsafe { ptr::read(self.storage[self.len].as_ptr()) })
+        }
+    }
+
+    /// Inserts `value` at position `index`, shifting all elements after it to
+    /// the right.
+    ///
+    /// # Error
+    ///
+    /// If this vector is full, an `Err` is returned, `value` is dropped and
+    /// the vector is left unchanged. Otherwise, `Ok` is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, value: T) -> Result<(), ()> {
+        assert!(index <= self.len, "insertion index is out of bounds");
+        self.push(value)?;
+        self.as_mut_slice()[index..].rotate_right(1);
+        Ok(())
+    }
+
+    /// Removes element at index, shifting all remaining elements to the left.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "removal index is out of bounds");
+        self.as_mut_slice()[index..].rotate_left(1);
+        self.pop().unwrap()
+    }
+
+    /// Removes the element at `index` and returns it, replacing it with the
+    /// last element of the vector. This does not preserve ordering, but is
+    /// O(1).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "removal index is out of bounds");
+        let last = self.len - 1;
+        self.as_mut_slice().swap(index, last);
+        self.pop().unwrap()
+    }
+
+    /// Shortens the vector, keeping the first `len` elements and dropping the
+    /// rest. If `len` is greater than the vector's current length, this has no
+    /// effect. Note that this method has no effect on the capacity of the
+    /// vector.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            let tail = &mut self.as_mut_slice()[len..] as *mut [T];
+            // Shrink first so that a panicking `drop` can't lead to the tail
+            // being dropped a second time.
+            self.len = len;
+            unsafe { ptr::drop_in_place(tail) };
+        }
+    }
+
+    /// Clears the vector, dropping all values. Note that this method has no
+    /// effect on the capacity of the vector.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Retains only the elements for which `f` returns `true`, preserving the
+    /// order of the retained elements. The other elements are dropped.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        let len = self.len;
+        let mut kept = 0;
+        // If `f` or a `drop` panics, the elements that haven't been looked at
+        // yet are leaked rather than dropped twice.
+        self.len = 0;
+        let base = self.storage.as_mut_ptr() as *mut T;
+        for i in 0..len {
+            unsafe {
+                let src = base.add(i);
+                if f(&*src) {
+                    ptr::copy(src, base.add(kept), 1);
+                    kept += 1;
+                    self.len = kept;
+                } else {
+                    ptr::drop_in_place(src);
+                }
+            }
+        }
+    }
+
+    /// Appends every value yielded by `iter` to the back of this vector.
+    ///
+    /// # Error
+    ///
+    /// If `iter` yields more values than the vector has room for, an `Err` is
+    /// returned and the vector is restored to its previous length, dropping
+    /// the values that were appended. Otherwise, `Ok` is returned.
+    pub fn try_extend<I: IntoIterator<Item = T>>(&mut self, iter: I) -> Result<(), ()> {
+        let len = self.len;
+        for value in iter {
+            if self.push(value).is_err() {
+                self.truncate(len);
+                return Err(());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, T: Clone + 'a> UninitStackVec<'a, T> {
+    /// Clones and appends every element of `other` to the back of this vector.
+    ///
+    /// # Error
+    ///
+    /// If the vector doesn't have room for all of `other`, an `Err` is
+    /// returned and the vector is left unchanged. Otherwise, `Ok` is returned.
+    pub fn extend_from_slice(&mut self, other: &[T]) -> Result<(), ()> {
+        if other.len() > self.capacity() - self.len {
+            return Err(());
+        }
+        self.try_extend(other.iter().cloned())
+    }
+}
+
+impl<'a, T: 'a> Drop for UninitStackVec<'a, T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<'a, T> Deref for UninitStackVec<'a, T> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<'a, T> DerefMut for UninitStackVec<'a, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for UninitStackVec<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T> IntoIterator for UninitStackVec<'a, T> {
+    type Item = T;
+    type IntoIter = UninitIntoIter<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        // The iterator takes over responsibility for dropping the elements.
+        let this = ManuallyDrop::new(self);
+        let back = this.len;
+        let storage = unsafe { ptr::read(&this.storage) };
+        UninitIntoIter {
+            storage,
+            front: 0,
+            back,
+        }
+    }
+}
+
+impl<'a, 'b, T> IntoIterator for &'a UninitStackVec<'b, T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_slice().iter()
+    }
+}
+
+impl<'a, 'b, T> IntoIterator for &'a mut UninitStackVec<'b, T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_mut_slice().iter_mut()
+    }
+}
+
+/// An iterator that moves elements out of an `UninitStackVec`. Elements that
+/// haven't been yielded are dropped along with the iterator.
+///
+/// This `struct` is created by the `into_iter` method on `UninitStackVec`
+/// (provided by the `IntoIterator` trait).
+pub struct UninitIntoIter<'a, T: 'a> {
+    storage: &'a mut [MaybeUninit<T>],
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T: 'a> Iterator for UninitIntoIter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.front == self.back {
+            None
+        } else {
+            self.front += 1;
+            Some(unsafe { ptr::read(self.storage[self.front - 1].as_ptr()) })
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<'a, T: 'a> DoubleEndedIterator for UninitIntoIter<'a, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.front == self.back {
+            None
+        } else {
+            self.back -= 1;
+            Some(unsafe { ptr::read(self.storage[self.back].as_ptr()) })
+        }
+    }
+}
+
+impl<'a, T: 'a> ExactSizeIterator for UninitIntoIter<'a, T> {}
+
+impl<'a, T: 'a> Drop for UninitIntoIter<'a, T> {
+    fn drop(&mut self) {
+        for _ in self.by_ref() {}
+    }
+}
@@ -0,0 +1,263 @@
+// Just enough of an ELF reader to turn a linked kernel into the flat image
+// the bootloader expects, i.e. what `objcopy -O binary` would have produced.
+use std::io;
+
+const MAGIC: &[u8; 4] = b"\x7fELF";
+
+const CLASS_32: u8 = 1;
+const CLASS_64: u8 = 2;
+const DATA_LSB: u8 = 1;
+
+/// Program header type of a loadable segment.
+const PT_LOAD: u32 = 1;
+
+/// A flat binary image built from the loadable segments of an ELF file.
+#[derive(Debug)]
+pub struct Image {
+    /// Physical address the first byte of `data` is meant to be loaded at.
+    pub base: u64,
+    /// Address execution starts at, taken from the ELF header.
+    pub entry: u64,
+    /// The flattened segments with any gaps between them zero-filled.
+    pub data: Vec<u8>,
+}
+
+/// The parts of a `PT_LOAD` program header needed for flattening.
+struct Segment {
+    offset: u64,
+    paddr: u64,
+    filesz: u64,
+}
+
+/// Returns `true` if `bytes` starts with the ELF magic number.
+pub fn is_elf(bytes: &[u8]) -> bool {
+    bytes.len() >= MAGIC.len() && &bytes[..MAGIC.len()] == MAGIC
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn field(bytes: &[u8], at: u64, len: usize) -> io::Result<&[u8]> {
+    let start = at as usize;
+    start
+        .checked_add(len)
+        .and_then(|end| bytes.get(start..end))
+        .ok_or_else(|| invalid("ELF file is truncated"))
+}
+
+fn u16_at(bytes: &[u8], at: u64) -> io::Result<u16> {
+    let b = field(bytes, at, 2)?;
+    Ok(b.iter().rev().fold(0, |acc, &b| acc << 8 | u16::from(b)))
+}
+
+fn u32_at(bytes: &[u8], at: u64) -> io::Result<u32> {
+    let b = field(bytes, at, 4)?;
+    Ok(b.iter().rev().fold(0, |acc, &b| acc << 8 | u32::from(b)))
+}
+
+fn u64_at(bytes: &[u8], at: u64) -> io::Result<u64> {
+    let b = field(bytes, at, 8)?;
+    Ok(b.iter().rev().fold(0, |acc, &b| acc << 8 | u64::from(b)))
+}
+
+/// Returns `base + index * stride`, or an `InvalidData` error if that doesn't
+/// fit in a `u64`.
+fn offset_of(base: u64, index: u64, stride: u64) -> io::Result<u64> {
+    index
+        .checked_mul(stride)
+        .and_then(|off| off.checked_add(base))
+        .ok_or_else(|| invalid("ELF program header offset overflows"))
+}
+
+/// Reads a word-sized field: 4 bytes for ELF32, 8 bytes for ELF64.
+fn word_at(bytes: &[u8], at: u64, wide: bool) -> io::Result<u64> {
+    if wide {
+        u64_at(bytes, at)
+    } else {
+        u32_at(bytes, at).map(u64::from)
+    }
+}
+
+/// Parses the program headers of `bytes` and returns its `PT_LOAD` segments.
+fn load_segments(bytes: &[u8]) -> io::Result<(u64, Vec<Segment>)> {
+    if !is_elf(bytes) {
+        return Err(invalid("missing ELF magic number"));
+    }
+    let wide = match field(bytes, 4, 1)?[0] {
+        CLASS_32 => false,
+        CLASS_64 => true,
+        _ => return Err(invalid("unknown ELF class")),
+    };
+    if field(bytes, 5, 1)?[0] != DATA_LSB {
+        return Err(invalid("only little-endian ELF files are supported"));
+    }
+
+    // The two classes only differ in the width of the address-sized fields,
+    // which shifts the offsets of everything after them.
+    let (phoff, phentsize, phnum) = if wide {
+        (0x20, 0x36, 0x38)
+    } else {
+        (0x1C, 0x2A, 0x2C)
+    };
+    let entry = word_at(bytes, 0x18, wide)?;
+    let phoff = word_at(bytes, phoff, wide)?;
+    let phentsize = u64::from(u16_at(bytes, phentsize)?);
+    let phnum = u64::from(u16_at(bytes, phnum)?);
+
+    let (offset, paddr, filesz) = if wide {
+        (0x08, 0x18, 0x20)
+    } else {
+        (0x04, 0x0C, 0x10)
+    };
+    let mut segments = Vec::new();
+    for i in 0..phnum {
+        let ph = offset_of(phoff, i, phentsize)?;
+        if u32_at(bytes, ph)? != PT_LOAD {
+            continue;
+        }
+        let segment = Segment {
+            offset: word_at(bytes, offset_of(ph, 1, offset)?, wide)?,
+            paddr: word_at(bytes, offset_of(ph, 1, paddr)?, wide)?,
+            filesz: word_at(bytes, offset_of(ph, 1, filesz)?, wide)?,
+        };
+        // Segments with nothing in the file (`.bss`) are left for the kernel
+        // to zero, just like `objcopy -O binary` does.
+        if segment.filesz > 0 {
+            segments.push(segment);
+        }
+    }
+
+    Ok((entry, segments))
+}
+
+/// Flattens the loadable segments of the ELF file in `bytes` into a single
+/// image based at the lowest segment load address. Gaps between segments are
+/// filled with zeroes.
+///
+/// # Errors
+///
+/// Returns an error of kind `InvalidData` if `bytes` isn't a little-endian
+/// ELF file, is truncated, has no loadable segments, or if the flattened
+/// image would be larger than `max_size` bytes.
+pub fn flatten(bytes: &[u8], max_size: u64) -> io::Result<Image> {
+    let (entry, segments) = load_segments(bytes)?;
+
+    let base = segments
+        .iter()
+        .map(|s| s.paddr)
+        .min()
+        .ok_or_else(|| invalid("ELF file has no loadable segments"))?;
+    let end = segments
+        .iter()
+        .map(|s| s.paddr.saturating_add(s.filesz))
+        .max()
+        .unwrap();
+    let size = end - base;
+    if size > max_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "flattened image is {} bytes, larger than the maximum of {} bytes",
+                size, max_size
+            ),
+        ));
+    }
+
+    let mut data = vec![0u8; size as usize];
+    for segment in &segments {
+        let contents = field(bytes, segment.offset, segment.filesz as usize)?;
+        let start = (segment.paddr - base) as usize;
+        data[start..start + contents.len()].copy_from_slice(contents);
+    }
+
+    Ok(Image { base, entry, data })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a minimal little-endian ELF64 file with one `PT_LOAD` program
+    /// header per `(paddr, contents)` pair.
+    fn elf64(entry: u64, segments: &[(u64, &[u8])]) -> Vec<u8> {
+        let phoff = 0x40u64;
+        let data_start = phoff + 0x38 * segments.len() as u64;
+
+        let mut out = vec![0u8; 0x40];
+        out[..4].copy_from_slice(MAGIC);
+        out[4] = CLASS_64;
+        out[5] = DATA_LSB;
+        out[0x18..0x20].copy_from_slice(&entry.to_le_bytes());
+        out[0x20..0x28].copy_from_slice(&phoff.to_le_bytes());
+        out[0x36..0x38].copy_from_slice(&0x38u16.to_le_bytes());
+        out[0x38..0x3A].copy_from_slice(&(segments.len() as u16).to_le_bytes());
+
+        let mut offset = data_start;
+        for &(paddr, contents) in segments {
+            let mut ph = vec![0u8; 0x38];
+            ph[..4].copy_from_slice(&PT_LOAD.to_le_bytes());
+            ph[0x08..0x10].copy_from_slice(&offset.to_le_bytes());
+            ph[0x10..0x18].copy_from_slice(&paddr.to_le_bytes());
+            ph[0x18..0x20].copy_from_slice(&paddr.to_le_bytes());
+            ph[0x20..0x28].copy_from_slice(&(contents.len() as u64).to_le_bytes());
+            ph[0x28..0x30].copy_from_slice(&(contents.len() as u64).to_le_bytes());
+            out.extend_from_slice(&ph);
+            offset += contents.len() as u64;
+        }
+        for &(_, contents) in segments {
+            out.extend_from_slice(contents);
+        }
+        out
+    }
+
+    #[test]
+    fn detects_magic() {
+        assert!(is_elf(b"\x7fELF\x02\x01"));
+        assert!(!is_elf(b"\x7fEL"));
+        assert!(!is_elf(b"\x00\x00\x00\x00"));
+    }
+
+    #[test]
+    fn flattens_and_zero_fills_gaps() {
+        let elf = elf64(0x80000, &[(0x80004, &[5, 6]), (0x80000, &[1, 2])]);
+        let image = flatten(&elf, 1 << 20).expect("valid ELF");
+        assert_eq!(image.base, 0x80000);
+        assert_eq!(image.entry, 0x80000);
+        assert_eq!(image.data, vec![1, 2, 0, 0, 5, 6]);
+    }
+
+    #[test]
+    fn rejects_oversized_image() {
+        let elf = elf64(0x80000, &[(0x80000, &[1]), (0x80010, &[2])]);
+        let err = flatten(&elf, 0x10).expect_err("image is 17 bytes");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_truncated_file() {
+        let mut elf = elf64(0x80000, &[(0x80000, &[1, 2, 3, 4])]);
+        elf.truncate(elf.len() - 2);
+        assert!(flatten(&elf, 1 << 20).is_err());
+    }
+
+    #[test]
+    fn rejects_overflowing_program_headers() {
+        let mut elf = elf64(0x80000, &[(0x80000, &[1]), (0x80001, &[2])]);
+        elf[0x20..0x28].copy_from_slice(&(u64::MAX - 0x20).to_le_bytes());
+        let err = flatten(&elf, 1 << 20).expect_err("headers are out of range");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        elf[0x20..0x28].copy_from_slice(&0x40u64.to_le_bytes());
+        elf[0x36..0x38].copy_from_slice(&u16::MAX.to_le_bytes());
+        elf[0x38..0x3A].copy_from_slice(&u16::MAX.to_le_bytes());
+        let err = flatten(&elf, 1 << 20).expect_err("headers are truncated");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_no_segments() {
+        let elf = elf64(0x80000, &[]);
+        assert!(flatten(&elf, 1 << 20).is_err());
+    }
+}
@@ -1,27 +1,37 @@
 extern crate indicatif;
 extern crate serial;
+#[macro_use]
+extern crate serde_json;
 extern crate structopt;
 extern crate xmodem;
 #[macro_use]
 extern crate structopt_derive;
 
 use std::{
-    fs::{self, File},
-    io::{self, BufRead, BufReader, Write},
+    fs::File,
+    io::{self, Cursor, Read, Write},
     path::PathBuf,
+    process,
     time::Duration,
-    time::Instant,
 };
 
-use indicatif::{HumanDuration, ProgressBar, ProgressStyle};
 use serial::core::{BaudRate, CharSize, FlowControl, SerialDevice, SerialPortSettings, StopBits};
 use structopt::StructOpt;
 
-use xmodem::{Progress, Xmodem};
+use xmodem::Xmodem;
+
+use report::{Format, Report};
+use reset::ResetSequence;
 
+mod elf;
 mod input;
 mod parsers;
-use parsers::{parse_baud_rate, parse_flow_control, parse_stop_bits, parse_width};
+mod report;
+mod reset;
+use parsers::{parse_baud_rate, parse_flow_control, parse_size, parse_stop_bits, parse_width};
+
+/// Address the bootloader loads the received binary at and jumps to.
+const BOOTLOADER_LOAD_ADDR: u64 = 0x80000;
 
 #[derive(StructOpt, Debug)]
 #[structopt(about = "Write to TTY using the XMODEM protocol by default.")]
@@ -83,12 +93,89 @@ struct Opt {
 
     #[structopt(short = "r", long = "raw", help = "Disable XMODEM")]
     raw: bool,
+
+    #[structopt(
+        short = "m",
+        long = "max-size",
+        parse(try_from_str = "parse_size"),
+        help = "Refuse to send images larger than this many bytes",
+        default_value = "0x3F80000"
+    )]
+    max_size: u64,
+
+    #[structopt(
+        long = "reset-sequence",
+        parse(try_from_str),
+        help = "DTR/RTS toggles and delays (ms) to run before sending, e.g. 'dtr=1,rts=1,wait=100,rts=0'"
+    )]
+    reset_sequence: Option<ResetSequence>,
+
+    #[structopt(
+        long = "retry",
+        parse(try_from_str),
+        help = "Reset and resend up to this many more times if the XMODEM handshake times out",
+        default_value = "0"
+    )]
+    retry: u32,
+
+    #[structopt(long = "json", help = "Print a JSON summary instead of a progress bar")]
+    json: bool,
+
+    #[structopt(
+        long = "json-stream",
+        help = "Print one JSON progress event per line, then the JSON summary"
+    )]
+    json_stream: bool,
 }
 
-fn main() -> io::Result<()> {
-    let start = Instant::now();
-    let opt = Opt::from_args();
-    let mut serial = serial::open(&opt.tty_path).expect("path points to invalid TTY");
+/// Reads all of `input` and, if it is an ELF file, flattens its loadable
+/// segments into the binary image the bootloader expects.
+fn load_image<R: Read>(mut input: R, max_size: u64) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes)?;
+    if !elf::is_elf(&bytes) {
+        if bytes.len() as u64 > max_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "image is {} bytes, larger than the maximum of {} bytes",
+                    bytes.len(),
+                    max_size
+                ),
+            ));
+        }
+        return Ok(bytes);
+    }
+
+    let image = elf::flatten(&bytes, max_size)?;
+    if image.base != BOOTLOADER_LOAD_ADDR {
+        eprintln!(
+            "warning: ELF load address {:#x} doesn't match the bootloader's {:#x}",
+            image.base, BOOTLOADER_LOAD_ADDR
+        );
+    }
+    if image.entry != BOOTLOADER_LOAD_ADDR {
+        eprintln!(
+            "warning: ELF entry point {:#x} doesn't match the bootloader's {:#x}",
+            image.entry, BOOTLOADER_LOAD_ADDR
+        );
+    }
+    Ok(image.data)
+}
+
+/// Sends `data` to `to` using XMODEM, recording progress in `report`.
+fn transmit<R, W>(data: R, to: W, report: &mut Report) -> io::Result<usize>
+where
+    R: Read,
+    W: Read + Write,
+{
+    Xmodem::transmit_with_progress(data, to, |progress| report.progress(progress))
+}
+
+/// Opens and configures the TTY, then uploads the input to it. Returns the
+/// number of bytes written.
+fn run(opt: &Opt, report: &mut Report) -> io::Result<u64> {
+    let mut serial = serial::open(&opt.tty_path)?;
 
     let mut settings = serial.read_settings()?;
     settings.set_baud_rate(opt.baud_rate)?;
@@ -98,52 +185,58 @@ fn main() -> io::Result<()> {
 
     serial.write_settings(&settings)?;
     serial.set_timeout(Duration::from_secs(opt.timeout))?;
+    report.settings(&serial.read_settings()?);
 
-    let (mut reader, pb): (Box<dyn BufRead>, _) = match opt.input {
-        None => {
-            let pb = ProgressBar::new_spinner();
-            pb.set_style(ProgressStyle::default_spinner());
-            (Box::new(BufReader::new(io::stdin())), pb)
-        }
-        Some(ref input) => (
-            Box::new(BufReader::new(
-                File::open(input).expect("Error with input path"),
-            )),
-            ProgressBar::new(fs::metadata(input)?.len()),
-        ),
+    let image = match opt.input {
+        None => load_image(io::stdin(), opt.max_size)?,
+        Some(ref input) => load_image(File::open(input)?, opt.max_size)?,
     };
-    println!("Starting");
+    report.begin(image.len() as u64);
+    let mut reader = Cursor::new(image);
 
     if opt.raw {
-        let total_bytes = io::copy(&mut reader, &mut serial)?;
-        println!("Wrote {} bytes", total_bytes);
-    } else {
-        let mut count = 0;
-        let total_bytes =
-            Xmodem::transmit_with_progress(reader, serial, |progress| match progress {
-                Progress::Started => {
-                    pb.set_message("Starting transmission...");
-                }
-                Progress::Waiting => {
-                    pb.set_message("waiting");
-                }
-                Progress::Packet(_) => {
-                    io::stdout().flush().unwrap();
-                    match opt.input {
-                        None => pb.tick(),
-                        Some(_) => {
-                            count += 1;
-                            pb.set_position(count * 128) // each packet is 128 bytes
-                        }
-                    }
-                }
-            })?;
-        let msg = format!(
-            "Wrote {} bytes in {}",
-            total_bytes,
-            HumanDuration(start.elapsed())
-        );
-        pb.finish_with_message(&msg[..]);
+        if let Some(ref sequence) = opt.reset_sequence {
+            sequence.run(&mut serial)?;
+        }
+        return io::copy(&mut reader, &mut serial);
+    }
+
+    loop {
+        if let Some(ref sequence) = opt.reset_sequence {
+            sequence.run(&mut serial)?;
+        }
+        reader.set_position(0);
+        match transmit(&mut reader, &mut serial, report) {
+            // Only a timeout before the receiver acknowledged anything is a
+            // failed handshake. Once a packet is through, the receiver is
+            // mid-transfer and won't answer a fresh handshake.
+            Err(ref e)
+                if e.kind() == io::ErrorKind::TimedOut
+                    && report.packets() == 0
+                    && report.retries() < opt.retry =>
+            {
+                report.retry(opt.retry);
+            }
+            result => return result.map(|n| n as u64),
+        }
+    }
+}
+
+fn main() {
+    let opt = Opt::from_args();
+    let format = match (opt.json, opt.json_stream) {
+        (_, true) => Format::JsonStream,
+        (true, false) => Format::Json,
+        (false, false) => Format::Human,
+    };
+    let protocol = if opt.raw { "raw" } else { "xmodem" };
+
+    let mut report = Report::new(format, protocol);
+    let result = run(&opt, &mut report);
+    if let Err(e) = report.finish(result) {
+        if format == Format::Human {
+            eprintln!("Error: {:?}", e);
+        }
+        process::exit(1);
     }
-    Ok(())
 }
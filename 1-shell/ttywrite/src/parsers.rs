@@ -30,3 +30,11 @@ pub fn parse_flow_control(s: &str) -> Result<FlowControl, &str> {
 pub fn parse_baud_rate(s: &str) -> Result<BaudRate, ::std::num::ParseIntError> {
     Ok(BaudRate::from_speed(s.parse()?))
 }
+
+pub fn parse_size(s: &str) -> Result<u64, ::std::num::ParseIntError> {
+    if s.starts_with("0x") || s.starts_with("0X") {
+        u64::from_str_radix(&s[2..], 16)
+    } else {
+        s.parse()
+    }
+}
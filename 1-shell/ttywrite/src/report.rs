@@ -0,0 +1,248 @@
+// Reports the progress and outcome of an upload, either for a human watching
+// the terminal or as JSON for tools wrapping `ttywrite`.
+use std::{
+    io::{self, Write},
+    time::Instant,
+};
+
+use indicatif::{HumanDuration, ProgressBar};
+use serde_json::Value;
+use serial::core::{CharSize, FlowControl, Parity, SerialPortSettings, StopBits};
+use xmodem::Progress;
+
+/// How progress and results are reported.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// A progress bar and a closing summary line.
+    Human,
+    /// A single JSON object once the upload has finished.
+    Json,
+    /// One JSON event per line as the upload progresses, ending with the same
+    /// object `Json` prints.
+    JsonStream,
+}
+
+/// Collects statistics about an upload and reports them in a `Format`.
+pub struct Report {
+    format: Format,
+    protocol: &'static str,
+    pb: ProgressBar,
+    start: Instant,
+    settings: Value,
+    packets: u64,
+    retries: u32,
+}
+
+impl Report {
+    /// Returns a new report for an upload using `protocol` (`"xmodem"` or
+    /// `"raw"`). The clock for the reported duration starts now.
+    pub fn new(format: Format, protocol: &'static str) -> Report {
+        let pb = match format {
+            Format::Human => ProgressBar::new(0),
+            Format::Json | Format::JsonStream => ProgressBar::hidden(),
+        };
+        Report {
+            format,
+            protocol,
+            pb,
+            start: Instant::now(),
+            settings: Value::Null,
+            packets: 0,
+            retries: 0,
+        }
+    }
+
+    fn emit(&self, event: Value) {
+        if self.format == Format::JsonStream {
+            println!("{}", event);
+            io::stdout().flush().unwrap();
+        }
+    }
+
+    /// Records the serial port settings actually in effect.
+    pub fn settings<S: SerialPortSettings>(&mut self, settings: &S) {
+        self.settings = json!({
+            "baud_rate": settings.baud_rate().map(|b| b.speed()),
+            "char_size": settings.char_size().map(|c| match c {
+                CharSize::Bits5 => 5,
+                CharSize::Bits6 => 6,
+                CharSize::Bits7 => 7,
+                CharSize::Bits8 => 8,
+            }),
+            "parity": settings.parity().map(|p| match p {
+                Parity::ParityNone => "none",
+                Parity::ParityOdd => "odd",
+                Parity::ParityEven => "even",
+            }),
+            "stop_bits": settings.stop_bits().map(|s| match s {
+                StopBits::Stop1 => 1,
+                StopBits::Stop2 => 2,
+            }),
+            "flow_control": settings.flow_control().map(|f| match f {
+                FlowControl::FlowNone => "none",
+                FlowControl::FlowSoftware => "software",
+                FlowControl::FlowHardware => "hardware",
+            }),
+        });
+    }
+
+    /// Marks the start of an upload of `len` bytes.
+    pub fn begin(&mut self, len: u64) {
+        self.pb.set_length(len);
+        if self.format == Format::Human {
+            println!("Starting");
+        }
+        self.emit(json!({ "event": "begin", "bytes": len, "protocol": self.protocol }));
+    }
+
+    /// Records XMODEM progress.
+    pub fn progress(&mut self, progress: Progress) {
+        let event = self.record(progress);
+        self.emit(event);
+    }
+
+    /// Records XMODEM progress and returns the JSON event describing it.
+    fn record(&mut self, progress: Progress) -> Value {
+        match progress {
+            Progress::Started => {
+                self.pb.set_message("Starting transmission...");
+                json!({ "event": "started" })
+            }
+            Progress::Waiting => {
+                self.pb.set_message("waiting");
+                json!({ "event": "waiting" })
+            }
+            Progress::Packet(packet) => {
+                io::stdout().flush().unwrap();
+                self.packets += 1;
+                let written = self.packets * 128; // each packet is 128 bytes
+                self.pb.set_position(written);
+                json!({ "event": "packet", "packet": packet, "bytes": written })
+            }
+        }
+    }
+
+    /// Returns the number of packets the receiver has acknowledged since the
+    /// upload was last (re)started.
+    pub fn packets(&self) -> u64 {
+        self.packets
+    }
+
+    /// Returns the number of times the upload has been restarted.
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Records that the upload is being restarted after a handshake timeout.
+    pub fn retry(&mut self, max: u32) {
+        self.retries += 1;
+        self.packets = 0;
+        self.pb.set_position(0);
+        self.pb
+            .set_message(&format!("timed out, retry {}/{}", self.retries, max));
+        self.emit(json!({ "event": "retry", "attempt": self.retries, "max": max }));
+    }
+
+    /// Reports the outcome of the upload, `result` being the number of bytes
+    /// written on success. The error, if any, is passed through so the exit
+    /// status reflects it. The JSON formats include the error in the summary,
+    /// so the caller shouldn't print it again.
+    pub fn finish(self, result: io::Result<u64>) -> io::Result<()> {
+        if self.format == Format::Human {
+            let total_bytes = result?;
+            if self.protocol == "raw" {
+                println!("Wrote {} bytes", total_bytes);
+            } else {
+                let msg = format!(
+                    "Wrote {} bytes in {}",
+                    total_bytes,
+                    HumanDuration(self.start.elapsed())
+                );
+                self.pb.finish_with_message(&msg[..]);
+            }
+            return Ok(());
+        }
+
+        println!("{}", self.summary(&result));
+        result.map(|_| ())
+    }
+
+    /// Returns the JSON summary of the upload's outcome.
+    fn summary(&self, result: &io::Result<u64>) -> Value {
+        let elapsed = self.start.elapsed();
+        let mut summary = json!({
+            "event": "finish",
+            "protocol": self.protocol,
+            "settings": self.settings,
+            "packets": self.packets,
+            "retries": self.retries,
+            "duration_ms": elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis()),
+        });
+        match *result {
+            Ok(bytes) => {
+                summary["success"] = json!(true);
+                summary["bytes"] = json!(bytes);
+            }
+            Err(ref e) => {
+                summary["success"] = json!(false);
+                summary["error"] = json!({
+                    "kind": format!("{:?}", e.kind()),
+                    "message": e.to_string(),
+                });
+            }
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_events() {
+        let mut report = Report::new(Format::JsonStream, "xmodem");
+        assert_eq!(report.record(Progress::Waiting), json!({ "event": "waiting" }));
+        assert_eq!(report.record(Progress::Started), json!({ "event": "started" }));
+        assert_eq!(
+            report.record(Progress::Packet(1)),
+            json!({ "event": "packet", "packet": 1, "bytes": 128 })
+        );
+        assert_eq!(
+            report.record(Progress::Packet(2)),
+            json!({ "event": "packet", "packet": 2, "bytes": 256 })
+        );
+        assert_eq!(report.packets(), 2);
+    }
+
+    #[test]
+    fn successful_summary() {
+        let mut report = Report::new(Format::Json, "xmodem");
+        report.progress(Progress::Packet(1));
+        report.retry(3);
+        report.progress(Progress::Packet(1));
+
+        let summary = report.summary(&Ok(128));
+        assert_eq!(summary["event"], "finish");
+        assert_eq!(summary["protocol"], "xmodem");
+        assert_eq!(summary["settings"], Value::Null);
+        assert_eq!(summary["packets"], 1);
+        assert_eq!(summary["retries"], 1);
+        assert!(summary["duration_ms"].is_u64());
+        assert_eq!(summary["success"], true);
+        assert_eq!(summary["bytes"], 128);
+        assert!(summary.get("error").is_none());
+    }
+
+    #[test]
+    fn failed_summary() {
+        let report = Report::new(Format::Json, "raw");
+        let error = io::Error::new(io::ErrorKind::TimedOut, "no NAK");
+        let summary = report.summary(&Err(error));
+        assert_eq!(summary["event"], "finish");
+        assert_eq!(summary["protocol"], "raw");
+        assert_eq!(summary["success"], false);
+        assert_eq!(summary["error"], json!({ "kind": "TimedOut", "message": "no NAK" }));
+        assert!(summary.get("bytes").is_none());
+    }
+}
@@ -0,0 +1,113 @@
+// Drives a reset circuit wired to the adapter's modem-control lines so the Pi
+// can be put back into the bootloader without a manual power cycle.
+use std::{io, str::FromStr, thread, time::Duration};
+
+use serial::core::SerialDevice;
+
+/// A single step of a reset sequence.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    /// Drive the DTR line to the given level.
+    Dtr(bool),
+    /// Drive the RTS line to the given level.
+    Rts(bool),
+    /// Do nothing for the given duration.
+    Wait(Duration),
+}
+
+/// A sequence of DTR/RTS toggles and delays, parsed from a comma-separated
+/// list like `dtr=1,rts=1,wait=100,rts=0`. Delays are in milliseconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResetSequence(Vec<Step>);
+
+fn parse_level(s: &str) -> Result<bool, String> {
+    match s {
+        "1" | "high" | "on" => Ok(true),
+        "0" | "low" | "off" => Ok(false),
+        _ => Err(format!(
+            "invalid line level '{}': use 0/1, low/high or off/on",
+            s
+        )),
+    }
+}
+
+impl FromStr for Step {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Step, String> {
+        let mut parts = s.trim().splitn(2, '=');
+        let name = parts.next().unwrap_or("");
+        let value = parts
+            .next()
+            .ok_or_else(|| format!("step '{}' is missing '=<value>'", s))?;
+        match name {
+            "dtr" => parse_level(value).map(Step::Dtr),
+            "rts" => parse_level(value).map(Step::Rts),
+            "wait" => value
+                .parse()
+                .map(|ms| Step::Wait(Duration::from_millis(ms)))
+                .map_err(|_| format!("invalid delay '{}': expected milliseconds", value)),
+            _ => Err(format!(
+                "unknown step '{}': use 'dtr', 'rts' or 'wait'",
+                name
+            )),
+        }
+    }
+}
+
+impl FromStr for ResetSequence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ResetSequence, String> {
+        s.split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>()
+            .map(ResetSequence)
+    }
+}
+
+impl ResetSequence {
+    /// Runs every step of the sequence against `port`, in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if setting the level of a control line fails.
+    pub fn run<T: SerialDevice>(&self, port: &mut T) -> io::Result<()> {
+        for step in &self.0 {
+            match *step {
+                Step::Dtr(level) => port.set_dtr(level)?,
+                Step::Rts(level) => port.set_rts(level)?,
+                Step::Wait(duration) => thread::sleep(duration),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sequence() {
+        let seq: ResetSequence = "dtr=1, rts=low,wait=250,rts=on".parse().unwrap();
+        assert_eq!(
+            seq,
+            ResetSequence(vec![
+                Step::Dtr(true),
+                Step::Rts(false),
+                Step::Wait(Duration::from_millis(250)),
+                Step::Rts(true),
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_bad_steps() {
+        assert!("dtr".parse::<ResetSequence>().is_err());
+        assert!("dtr=2".parse::<ResetSequence>().is_err());
+        assert!("cts=1".parse::<ResetSequence>().is_err());
+        assert!("wait=soon".parse::<ResetSequence>().is_err());
+        assert!("dtr=1,".parse::<ResetSequence>().is_err());
+    }
+}
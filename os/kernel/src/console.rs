@@ -1,71 +1,168 @@
 use std::fmt;
 use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use pi::uart::MiniUart;
+use pi::framebuffer::{self, Framebuffer, TextConsole};
+use pi::uart::{MiniUart, Pl011, SerialPort};
 
 use mutex::Mutex;
 
-/// A global singleton allowing read/write access to the console.
-pub struct Console {
-    inner: Option<MiniUart>,
+/// The serial port used by the global console.
+pub type Port = &'static mut (SerialPort + Send);
+
+/// A console over a serial port, whose output can be mirrored onto a screen.
+pub struct Console<S: SerialPort> {
+    port: Option<S>,
+    /// Opens the port the first time it's used, if none was set.
+    open: fn() -> S,
+    screen: Option<TextConsole<'static>>,
 }
 
-impl Console {
-    /// Creates a new instance of `Console`.
-    const fn new() -> Console {
-        Console { inner: None }
+impl<S: SerialPort> Console<S> {
+    /// Creates a new instance of `Console` which opens its serial port with
+    /// `open` when first used.
+    pub const fn new(open: fn() -> S) -> Console<S> {
+        Console {
+            port: None,
+            open,
+            screen: None,
+        }
     }
 
     /// Initializes the console if it's not already initialized.
     #[inline]
     fn initialize(&mut self) {
-        if self.inner.is_none() {
-            self.inner = Some(MiniUart::new());
+        if self.port.is_none() {
+            self.port = Some((self.open)());
         }
     }
 
-    /// Returns a mutable borrow to the inner `MiniUart`, initializing it as
-    /// needed.
-    fn inner(&mut self) -> &mut MiniUart {
+    /// Returns a mutable borrow to the serial port, opening it as needed.
+    fn inner(&mut self) -> &mut S {
         self.initialize();
-        self.inner.as_mut().unwrap()
+        self.port.as_mut().unwrap()
+    }
+
+    /// Makes the console use `port` from now on.
+    pub fn set_port(&mut self, port: S) {
+        self.port = Some(port);
     }
 
-    /// Reads a byte from the UART device, blocking until a byte is available.
+    /// Mirrors everything written to the console from now on onto `screen`.
+    pub fn mirror_to(&mut self, screen: TextConsole<'static>) {
+        self.screen = Some(screen);
+    }
+
+    /// Reads a byte from the serial port, blocking until a byte is available.
     pub fn read_byte(&mut self) -> u8 {
         self.inner().read_byte()
     }
 
-    /// Writes the byte `byte` to the UART device.
+    /// Writes the byte `byte` to the serial port, and to the screen if the
+    /// console is mirrored.
     pub fn write_byte(&mut self, byte: u8) {
-        self.inner().write_byte(byte)
+        self.inner().write_byte(byte);
+        if let Some(screen) = self.screen.as_mut() {
+            screen.write_byte(byte);
+        }
     }
 }
 
-impl io::Read for Console {
+// Reads wait at most the port's read timeout for the first byte, then read
+// as many bytes as are ready.
+impl<S: SerialPort> io::Read for Console<S> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.inner().read(buf)
+        let port = self.inner();
+        if port.wait_for_byte().is_err() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "Read timed out"));
+        }
+
+        let mut read = 0;
+        while read < buf.len() && port.has_byte() {
+            buf[read] = port.read_byte();
+            read += 1;
+        }
+        Ok(read)
     }
 }
 
-impl io::Write for Console {
+impl<S: SerialPort> io::Write for Console<S> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.inner().write(buf)
+        for &byte in buf {
+            self.write_byte(byte);
+        }
+        Ok(buf.len())
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.inner().flush()
+        self.inner().flush();
+        Ok(())
     }
 }
 
-impl fmt::Write for Console {
+impl<S: SerialPort> fmt::Write for Console<S> {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        self.inner().write_str(s)
+        {
+            let port = self.inner();
+            for &byte in s.as_bytes() {
+                if byte == b'\n' {
+                    port.write_byte(b'\r');
+                }
+                port.write_byte(byte);
+            }
+        }
+        if let Some(screen) = self.screen.as_mut() {
+            screen.write_str(s)?;
+        }
+        Ok(())
+    }
+}
+
+/// Initializes the mini UART and returns it. Only called by `CONSOLE`, once.
+fn open_mini_uart() -> Port {
+    static mut MINI_UART: Option<MiniUart> = None;
+    unsafe {
+        MINI_UART = Some(MiniUart::new());
+        MINI_UART.as_mut().unwrap()
     }
 }
 
 /// Global `Console` singleton.
-pub static CONSOLE: Mutex<Console> = Mutex::new(Console::new());
+///
+/// The console uses the mini UART unless another port is set with
+/// `Console::set_port` before it's first used.
+pub static CONSOLE: Mutex<Console<Port>> = Mutex::new(Console::new(open_mini_uart));
+
+/// Switches the console to the PL011 UART, `UART0`, initializing it.
+///
+/// # Errors
+///
+/// The PL011 is only initialized once: every call after the first returns
+/// `Err(())` and leaves the console as it is.
+pub fn use_pl011() -> Result<(), ()> {
+    static INITIALIZED: AtomicBool = AtomicBool::new(false);
+    static mut PL011: Option<Pl011> = None;
+    if INITIALIZED.swap(true, Ordering::SeqCst) {
+        return Err(());
+    }
+    let mut console = CONSOLE.lock();
+    // Only the first call gets here, so nothing else refers to `PL011` yet.
+    unsafe {
+        PL011 = Some(Pl011::new());
+        console.set_port(PL011.as_mut().unwrap());
+    }
+    Ok(())
+}
+
+/// Allocates a `width` by `height` framebuffer and mirrors the console onto
+/// it, so that output also appears on the display.
+pub fn mirror_to_screen(width: u32, height: u32) -> Result<(), framebuffer::Error> {
+    let framebuffer = Framebuffer::new(width, height)?;
+    CONSOLE
+        .lock()
+        .mirror_to(TextConsole::new(framebuffer.into_pixels()));
+    Ok(())
+}
 
 /// Internal function called by the `kprint[ln]!` macros.
 #[doc(hidden)]
@@ -92,3 +189,25 @@ pub macro kprintln {
 pub macro kprint($($arg:tt)*) {
     _print(format_args!($($arg)*))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+    use std::io::Read;
+
+    use pi::uart::Loopback;
+
+    use super::Console;
+
+    #[test]
+    fn console_round_trips_through_its_port() {
+        let mut console = Console::new(Loopback::new);
+        console.write_str("hi\n").unwrap();
+        console.write_byte(b'!');
+
+        let mut buf = [0; 8];
+        assert_eq!(console.read(&mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"hi\r\n!");
+        assert!(console.read(&mut buf).is_err());
+    }
+}
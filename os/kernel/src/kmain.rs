@@ -43,5 +43,10 @@ use console::kprintln;
 #[cfg(not(test))]
 pub extern "C" fn kmain() {
     ALLOCATOR.initialize();
+    // The firmware allocates a framebuffer whether or not a display is
+    // attached, so drawing to it is opt-in. If the allocation fails anyway,
+    // output only goes to the UART.
+    #[cfg(feature = "screen")]
+    let _ = console::mirror_to_screen(1024, 768);
     shell::shell("> ");
 }
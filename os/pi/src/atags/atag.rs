@@ -3,13 +3,19 @@ use atags::raw;
 // use std::ffi::CStr;
 // use std::os::raw::c_char;
 
-pub use atags::raw::{Core, Mem};
+pub use atags::raw::{Core, Initrd, Mem, Ramdisk, Revision, Serial, VideoLfb, VideoText};
 
 /// An ATAG.
 #[derive(Debug, Copy, Clone)]
 pub enum Atag {
     Core(raw::Core),
     Mem(raw::Mem),
+    VideoText(raw::VideoText),
+    Ramdisk(raw::Ramdisk),
+    Initrd(raw::Initrd),
+    Serial(raw::Serial),
+    Revision(raw::Revision),
+    VideoLfb(raw::VideoLfb),
     Cmd(&'static str),
     Unknown(u32),
     None,
@@ -32,6 +38,54 @@ impl Atag {
         }
     }
 
+    /// Returns `Some` if this is a `VideoText` ATAG. Otherwise returns `None`.
+    pub fn video_text(self) -> Option<VideoText> {
+        match self {
+            Atag::VideoText(video_text) => Some(video_text),
+            _ => None,
+        }
+    }
+
+    /// Returns `Some` if this is a `Ramdisk` ATAG. Otherwise returns `None`.
+    pub fn ramdisk(self) -> Option<Ramdisk> {
+        match self {
+            Atag::Ramdisk(ramdisk) => Some(ramdisk),
+            _ => None,
+        }
+    }
+
+    /// Returns `Some` if this is an `Initrd` ATAG. Otherwise returns `None`.
+    pub fn initrd(self) -> Option<Initrd> {
+        match self {
+            Atag::Initrd(initrd) => Some(initrd),
+            _ => None,
+        }
+    }
+
+    /// Returns `Some` if this is a `Serial` ATAG. Otherwise returns `None`.
+    pub fn serial(self) -> Option<Serial> {
+        match self {
+            Atag::Serial(serial) => Some(serial),
+            _ => None,
+        }
+    }
+
+    /// Returns `Some` if this is a `Revision` ATAG. Otherwise returns `None`.
+    pub fn revision(self) -> Option<Revision> {
+        match self {
+            Atag::Revision(revision) => Some(revision),
+            _ => None,
+        }
+    }
+
+    /// Returns `Some` if this is a `VideoLfb` ATAG. Otherwise returns `None`.
+    pub fn video_lfb(self) -> Option<VideoLfb> {
+        match self {
+            Atag::VideoLfb(video_lfb) => Some(video_lfb),
+            _ => None,
+        }
+    }
+
     /// Returns `Some` with the command line string if this is a `Cmd` ATAG.
     /// Otherwise returns `None`.
     pub fn cmd(self) -> Option<&'static str> {
@@ -48,6 +102,12 @@ impl<'a> From<&'a raw::Atag> for Atag {
             match (atag.tag, &atag.kind) {
                 (raw::Atag::CORE, &raw::Kind { core }) => Atag::Core(core),
                 (raw::Atag::MEM, &raw::Kind { mem }) => Atag::Mem(mem),
+                (raw::Atag::VIDEOTEXT, &raw::Kind { video_text }) => Atag::VideoText(video_text),
+                (raw::Atag::RAMDISK, &raw::Kind { ramdisk }) => Atag::Ramdisk(ramdisk),
+                (raw::Atag::INITRD2, &raw::Kind { initrd }) => Atag::Initrd(initrd),
+                (raw::Atag::SERIAL, &raw::Kind { serial }) => Atag::Serial(serial),
+                (raw::Atag::REVISION, &raw::Kind { revision }) => Atag::Revision(revision),
+                (raw::Atag::VIDEOLFB, &raw::Kind { video_lfb }) => Atag::VideoLfb(video_lfb),
                 (raw::Atag::CMDLINE, &raw::Kind { ref cmd }) => {
                     let ptr = &(*cmd).cmd as *const u8;
                     // no access to std::ffi here it looks like, if we did:
@@ -1,5 +1,7 @@
 mod raw;
 mod atag;
+#[cfg(test)]
+mod tests;
 
 pub use self::atag::*;
 
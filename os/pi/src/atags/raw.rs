@@ -36,6 +36,12 @@ impl Atag {
 pub union Kind {
     pub core: Core,
     pub mem: Mem,
+    pub video_text: VideoText,
+    pub ramdisk: Ramdisk,
+    pub initrd: Initrd,
+    pub serial: Serial,
+    pub revision: Revision,
+    pub video_lfb: VideoLfb,
     pub cmd: Cmd,
 }
 
@@ -56,6 +62,86 @@ pub struct Mem {
     pub start: u32,
 }
 
+/// A `VIDEOTEXT` ATAG, describing a VGA text display.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct VideoText {
+    pub x: u8,
+    pub y: u8,
+    pub video_page: u16,
+    pub video_mode: u8,
+    pub video_cols: u8,
+    pub video_ega_bx: u16,
+    pub video_lines: u8,
+    pub video_isvga: u8,
+    pub video_points: u16,
+}
+
+/// A `RAMDISK` ATAG, describing the RAM disk the kernel should create.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Ramdisk {
+    /// Bit 0: load the RAM disk. Bit 1: prompt for the RAM disk.
+    pub flags: u32,
+    /// The size of the RAM disk, in KiB.
+    pub size: u32,
+    /// The block number to load the RAM disk from.
+    pub start: u32,
+}
+
+/// An `INITRD2` ATAG, locating the initial RAM disk in memory.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Initrd {
+    /// The physical address of the initial RAM disk.
+    pub start: u32,
+    /// The size of the initial RAM disk, in bytes.
+    pub size: u32,
+}
+
+/// A `SERIAL` ATAG, holding the board's 64-bit serial number.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Serial {
+    pub low: u32,
+    pub high: u32,
+}
+
+impl Serial {
+    /// Returns the serial number.
+    pub fn number(&self) -> u64 {
+        (self.high as u64) << 32 | self.low as u64
+    }
+}
+
+/// A `REVISION` ATAG, holding the board's revision code.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Revision {
+    pub rev: u32,
+}
+
+/// A `VIDEOLFB` ATAG, describing a linear framebuffer.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct VideoLfb {
+    pub width: u16,
+    pub height: u16,
+    pub depth: u16,
+    /// The number of bytes per row.
+    pub line_length: u16,
+    pub base: u32,
+    pub size: u32,
+    pub red_size: u8,
+    pub red_pos: u8,
+    pub green_size: u8,
+    pub green_pos: u8,
+    pub blue_size: u8,
+    pub blue_pos: u8,
+    pub rsvd_size: u8,
+    pub rsvd_pos: u8,
+}
+
 /// A `CMDLINE` ATAG.
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -0,0 +1,142 @@
+use core::mem;
+
+use super::raw;
+use super::{Atag, Initrd, Serial};
+
+/// Parses the ATAG laid out in `words`, which are padded to the size of the
+/// largest ATAG.
+fn parse(words: &[u32; 8]) -> Atag {
+    assert!(mem::size_of::<raw::Atag>() <= mem::size_of::<[u32; 8]>());
+    Atag::from(unsafe { &*(words as *const [u32; 8] as *const raw::Atag) })
+}
+
+#[test]
+fn atag_accessors_match_their_variant() {
+    let serial = Atag::Serial(Serial {
+        low: 0xDEAD_BEEF,
+        high: 0x0000_00C0,
+    });
+    assert_eq!(serial.serial().map(|s| s.number()), Some(0xC0_DEAD_BEEF));
+    assert!(serial.initrd().is_none());
+
+    let initrd = Atag::Initrd(Initrd {
+        start: 0x0200_0000,
+        size: 0x8000,
+    });
+    assert_eq!(
+        initrd.initrd().map(|i| (i.start, i.size)),
+        Some((0x0200_0000, 0x8000))
+    );
+    assert!(initrd.revision().is_none() && initrd.mem().is_none());
+}
+
+#[test]
+fn raw_atags_have_the_documented_sizes() {
+    assert_eq!(mem::size_of::<raw::Core>(), 12);
+    assert_eq!(mem::size_of::<raw::Mem>(), 8);
+    assert_eq!(mem::size_of::<raw::VideoText>(), 12);
+    assert_eq!(mem::size_of::<raw::Ramdisk>(), 12);
+    assert_eq!(mem::size_of::<raw::Initrd>(), 8);
+    assert_eq!(mem::size_of::<raw::Serial>(), 8);
+    assert_eq!(mem::size_of::<raw::Revision>(), 4);
+    assert_eq!(mem::size_of::<raw::VideoLfb>(), 24);
+    assert_eq!(mem::size_of::<raw::Atag>(), 32);
+}
+
+#[test]
+fn raw_atags_convert_to_their_variant() {
+    let core = parse(&[5, raw::Atag::CORE, 1, 4096, 0x0100, 0, 0, 0])
+        .core()
+        .expect("a CORE ATAG");
+    assert_eq!(
+        (core.flags, core.page_size, core.root_dev),
+        (1, 4096, 0x0100)
+    );
+
+    let mem = parse(&[4, raw::Atag::MEM, 0x3C00_0000, 0x1000, 0, 0, 0, 0])
+        .mem()
+        .expect("a MEM ATAG");
+    assert_eq!((mem.size, mem.start), (0x3C00_0000, 0x1000));
+
+    let ramdisk = parse(&[5, raw::Atag::RAMDISK, 0b01, 4096, 32, 0, 0, 0])
+        .ramdisk()
+        .expect("a RAMDISK ATAG");
+    assert_eq!(
+        (ramdisk.flags, ramdisk.size, ramdisk.start),
+        (0b01, 4096, 32)
+    );
+
+    let initrd = parse(&[4, raw::Atag::INITRD2, 0x0200_0000, 0x8000, 0, 0, 0, 0])
+        .initrd()
+        .expect("an INITRD2 ATAG");
+    assert_eq!((initrd.start, initrd.size), (0x0200_0000, 0x8000));
+
+    let serial = parse(&[4, raw::Atag::SERIAL, 0xDEAD_BEEF, 0xC0, 0, 0, 0, 0])
+        .serial()
+        .expect("a SERIAL ATAG");
+    assert_eq!(serial.number(), 0xC0_DEAD_BEEF);
+
+    let revision = parse(&[3, raw::Atag::REVISION, 0x00A0_20D3, 0, 0, 0, 0, 0])
+        .revision()
+        .expect("a REVISION ATAG");
+    assert_eq!(revision.rev, 0x00A0_20D3);
+
+    let cmd = [4, raw::Atag::CMDLINE, 0x746F_6F72, 0, 0, 0, 0, 0];
+    assert_eq!(parse(&cmd).cmd(), Some("root"));
+
+    assert!(match parse(&[0, raw::Atag::NONE, 0, 0, 0, 0, 0, 0]) {
+        Atag::None => true,
+        _ => false,
+    });
+    assert!(match parse(&[2, 0x5441_00FF, 0, 0, 0, 0, 0, 0]) {
+        Atag::Unknown(0x5441_00FF) => true,
+        _ => false,
+    });
+}
+
+#[test]
+fn raw_video_atags_unpack_their_fields() {
+    let words = [
+        5,
+        raw::Atag::VIDEOTEXT,
+        0x0003_1902,
+        0x1234_5003,
+        0x0010_0119,
+        0,
+        0,
+        0,
+    ];
+    let text = parse(&words).video_text().expect("a VIDEOTEXT ATAG");
+    assert_eq!((text.x, text.y, text.video_page), (0x02, 0x19, 0x0003));
+    assert_eq!(
+        (text.video_mode, text.video_cols, text.video_ega_bx),
+        (0x03, 0x50, 0x1234)
+    );
+    assert_eq!(
+        (text.video_lines, text.video_isvga, text.video_points),
+        (0x19, 0x01, 0x0010)
+    );
+
+    let words = [
+        8,
+        raw::Atag::VIDEOLFB,
+        0x0300_0400,
+        0x1000_0020,
+        0x3E40_2000,
+        0x0030_0000,
+        0x0808_1008,
+        0x1808_0008,
+    ];
+    let lfb = parse(&words).video_lfb().expect("a VIDEOLFB ATAG");
+    assert_eq!((lfb.width, lfb.height), (1024, 768));
+    assert_eq!((lfb.depth, lfb.line_length), (32, 4096));
+    assert_eq!((lfb.base, lfb.size), (0x3E40_2000, 0x0030_0000));
+    assert_eq!(
+        (lfb.red_size, lfb.red_pos, lfb.green_size, lfb.green_pos),
+        (8, 16, 8, 8)
+    );
+    assert_eq!(
+        (lfb.blue_size, lfb.blue_pos, lfb.rsvd_size, lfb.rsvd_pos),
+        (8, 0, 8, 24)
+    );
+}
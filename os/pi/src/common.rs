@@ -1,6 +1,22 @@
 /// The address where I/O peripherals are mapped to.
 pub const IO_BASE: usize = 0x3F000000;
 
+/// Returns a reference to the `T` mapped at physical address `addr`.
+///
+/// With the `mock` feature enabled, `addr` is translated to the host memory
+/// simulating it, which must have been mapped with `volatile::mock::map`.
+///
+/// # Safety
+///
+/// The caller must ensure that a `T` is mapped at `addr` and that the
+/// returned reference doesn't alias another live mutable reference.
+#[inline(always)]
+pub unsafe fn mmio<T>(addr: usize) -> &'static mut T {
+    #[cfg(feature = "mock")]
+    let addr = ::volatile::mock::translate(addr);
+    &mut *(addr as *mut T)
+}
+
 /// Generates `pub enums` with no variants for each `ident` passed in.
 pub macro states($($name:ident),*) {
     $(
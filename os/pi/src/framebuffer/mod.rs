@@ -0,0 +1,230 @@
+pub mod font;
+mod text;
+
+pub use self::text::TextConsole;
+
+use volatile::prelude::*;
+use volatile::VolatileSlice;
+
+use mailbox::{
+    self, AllocateBuffer, GetPitch, Message, SetDepth, SetPhysicalSize, SetPixelOrder,
+    SetVirtualOffset, SetVirtualSize,
+};
+
+/// The number of bits per pixel of the framebuffers allocated by this module.
+const DEPTH: u32 = 32;
+
+/// The alignment requested for the framebuffer, in bytes.
+const ALIGNMENT: u32 = 16;
+
+/// A 24-bit RGB color, as `0x00RRGGBB`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color(pub u32);
+
+impl Color {
+    pub const BLACK: Color = Color(0x000000);
+    pub const WHITE: Color = Color(0xFFFFFF);
+
+    /// Returns the color with the components `r`, `g` and `b`.
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color((r as u32) << 16 | (g as u32) << 8 | b as u32)
+    }
+}
+
+/// An error allocating a framebuffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The property interface failed.
+    Mailbox(mailbox::Error),
+    /// The VideoCore didn't allocate a framebuffer matching the request.
+    Unsupported,
+}
+
+impl From<mailbox::Error> for Error {
+    fn from(error: mailbox::Error) -> Error {
+        Error::Mailbox(error)
+    }
+}
+
+/// A framebuffer allocated by the VideoCore and scanned out to the display.
+#[derive(Debug)]
+pub struct Framebuffer {
+    /// The ARM physical address of the framebuffer.
+    base: usize,
+    width: usize,
+    height: usize,
+    /// The number of bytes per row.
+    pitch: usize,
+}
+
+impl Framebuffer {
+    /// Asks the VideoCore for a `width` by `height` framebuffer with 32 bits
+    /// per pixel, and returns it.
+    ///
+    /// Returns `Error::Unsupported` if the VideoCore allocated no framebuffer,
+    /// or one with another size or depth.
+    pub fn new(width: u32, height: u32) -> Result<Framebuffer, Error> {
+        let mut message = Message::new();
+        let physical = message.push(SetPhysicalSize { width, height })?;
+        let virt = message.push(SetVirtualSize { width, height })?;
+        message.push(SetVirtualOffset { x: 0, y: 0 })?;
+        let depth = message.push(SetDepth {
+            bits_per_pixel: DEPTH,
+        })?;
+        // With BGR order, a little-endian 32-bit pixel reads as `0x00RRGGBB`.
+        message.push(SetPixelOrder { order: 0 })?;
+        let buffer = message.push(AllocateBuffer {
+            alignment: ALIGNMENT,
+        })?;
+        let pitch = message.push(GetPitch)?;
+        message.send()?;
+
+        let physical = message.response(physical)?;
+        let virt = message.response(virt)?;
+        let buffer = message.response(buffer)?;
+        let pitch = message.response(pitch)? as usize;
+        if physical.width != width
+            || physical.height != height
+            || virt != physical
+            || message.response(depth)? != DEPTH
+            || buffer.base == 0
+            || pitch * height as usize > buffer.size as usize
+        {
+            return Err(Error::Unsupported);
+        }
+
+        Ok(Framebuffer {
+            // The VideoCore returns a bus address; clear the cache alias bits.
+            base: (buffer.base & 0x3FFF_FFFF) as usize,
+            width: width as usize,
+            height: height as usize,
+            pitch,
+        })
+    }
+
+    /// Returns the width of the framebuffer, in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the framebuffer, in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the number of bytes per row of the framebuffer, which may be
+    /// more than the width of a row.
+    pub fn pitch(&self) -> usize {
+        self.pitch
+    }
+
+    /// Returns the number of bits per pixel of the framebuffer.
+    pub fn depth(&self) -> u32 {
+        DEPTH
+    }
+
+    /// Consumes the `Framebuffer` and returns its pixels.
+    pub fn into_pixels(self) -> PixelBuffer<'static> {
+        let stride = self.pitch / 4;
+        let pixels =
+            unsafe { VolatileSlice::from_raw_parts(self.base as *mut u32, stride * self.height) };
+        PixelBuffer::new(pixels, self.width, self.height, stride)
+    }
+}
+
+/// A rectangle of 32-bit pixels in memory, such as a framebuffer's. Every
+/// access to the pixels is volatile, since the display scans them out.
+#[derive(Debug)]
+pub struct PixelBuffer<'a> {
+    pixels: VolatileSlice<'a, u32>,
+    width: usize,
+    height: usize,
+    /// The number of pixels from the start of a row to the start of the next.
+    stride: usize,
+}
+
+impl<'a> PixelBuffer<'a> {
+    /// Returns a `width` by `height` buffer whose rows start every `stride`
+    /// pixels of `pixels`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `stride` is less than `width`, or if `pixels` is too short.
+    pub fn new(
+        pixels: VolatileSlice<'a, u32>,
+        width: usize,
+        height: usize,
+        stride: usize,
+    ) -> PixelBuffer<'a> {
+        assert!(stride >= width, "stride is shorter than a row");
+        assert!(
+            height == 0 || pixels.len() >= (height - 1) * stride + width,
+            "pixel buffer is too short"
+        );
+        PixelBuffer {
+            pixels,
+            width,
+            height,
+            stride,
+        }
+    }
+
+    /// Returns the width of the buffer, in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the buffer, in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the color of the pixel at (`x`, `y`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pixel is outside of the buffer.
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        assert!(x < self.width && y < self.height, "pixel is out of bounds");
+        Color(self.pixels.read(y * self.stride + x))
+    }
+
+    /// Sets the pixel at (`x`, `y`) to `color`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pixel is outside of the buffer.
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+        assert!(x < self.width && y < self.height, "pixel is out of bounds");
+        self.pixels.write(y * self.stride + x, color.0);
+    }
+
+    /// Fills the `width` by `height` rectangle whose top-left pixel is at
+    /// (`x`, `y`) with `color`. The parts of the rectangle outside of the
+    /// buffer are ignored.
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        let right = self.width.min(x.saturating_add(width));
+        let bottom = self.height.min(y.saturating_add(height));
+        for row in y..bottom {
+            let start = row * self.stride;
+            self.pixels
+                .sub_slice(start + x.min(right), start + right)
+                .fill(color.0);
+        }
+    }
+
+    /// Moves the contents of the buffer up by `rows` rows, and fills the rows
+    /// uncovered at the bottom with `fill`.
+    pub fn scroll_up(&mut self, rows: usize, fill: Color) {
+        let rows = rows.min(self.height);
+        for y in rows..self.height {
+            let (src, dst) = (y * self.stride, (y - rows) * self.stride);
+            for x in 0..self.width {
+                let pixel = self.pixels.read(src + x);
+                self.pixels.write(dst + x, pixel);
+            }
+        }
+        let (width, height) = (self.width, self.height);
+        self.fill_rect(0, height - rows, width, rows, fill);
+    }
+}
@@ -0,0 +1,349 @@
+use core::fmt;
+
+use super::font;
+use super::{Color, PixelBuffer};
+
+/// The colors selected by ANSI escape codes: the 8 normal colors, then their
+/// bright versions. These are the colors of the VGA text mode.
+const PALETTE: [Color; 16] = [
+    Color(0x000000),
+    Color(0xAA0000),
+    Color(0x00AA00),
+    Color(0xAA5500),
+    Color(0x0000AA),
+    Color(0xAA00AA),
+    Color(0x00AAAA),
+    Color(0xAAAAAA),
+    Color(0x555555),
+    Color(0xFF5555),
+    Color(0x55FF55),
+    Color(0xFFFF55),
+    Color(0x5555FF),
+    Color(0xFF55FF),
+    Color(0x55FFFF),
+    Color(0xFFFFFF),
+];
+
+/// The index in `PALETTE` of the default foreground color.
+const DEFAULT_FOREGROUND: usize = 7;
+
+/// The index in `PALETTE` of the default background color.
+const DEFAULT_BACKGROUND: usize = 0;
+
+/// The maximum number of parameters of an escape sequence. Any further
+/// parameters are ignored.
+const MAX_PARAMS: usize = 8;
+
+/// The state of the escape sequence parser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Characters are printed.
+    Normal,
+    /// An `ESC` was received.
+    Escape,
+    /// An `ESC [` was received: parameters follow, then the final byte.
+    Csi,
+}
+
+/// A text console that renders characters onto a `PixelBuffer` with the
+/// bitmap font in `font`.
+///
+/// The console wraps long lines and scrolls when the cursor goes past the
+/// last row. It understands `\n`, `\r`, `\t` and backspace, and the following
+/// ANSI escape sequences, where `n` is a number:
+///
+///   * `ESC [ n ; ... m`: select graphic rendition. Supported are 0 (reset),
+///     1 (bold, which brightens the foreground), 22 (normal intensity),
+///     30-37 and 90-97 (foreground), 39 (default foreground), 40-47 and
+///     100-107 (background) and 49 (default background).
+///   * `ESC [ n J`: clear from the cursor to the end of the screen (0) or
+///     the entire screen (2).
+///   * `ESC [ n K`: clear from the cursor to the end of the line (0) or the
+///     entire line (2).
+///   * `ESC [ row ; column H`: move the cursor, counting from 1.
+///   * `ESC [ n A`, `B`, `C`, `D`: move the cursor up, down, right or left.
+///
+/// Other escape sequences are ignored.
+pub struct TextConsole<'a> {
+    pixels: PixelBuffer<'a>,
+    columns: usize,
+    rows: usize,
+    column: usize,
+    row: usize,
+    /// The index in `PALETTE` of the foreground color.
+    foreground: usize,
+    /// The index in `PALETTE` of the background color.
+    background: usize,
+    bold: bool,
+    state: State,
+    params: [u16; MAX_PARAMS],
+    /// The index of the parameter being parsed.
+    param: usize,
+}
+
+impl<'a> TextConsole<'a> {
+    /// Returns a new console drawing onto `pixels`, which is cleared.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pixels` is smaller than a single glyph.
+    pub fn new(pixels: PixelBuffer<'a>) -> TextConsole<'a> {
+        let columns = pixels.width() / font::WIDTH;
+        let rows = pixels.height() / font::HEIGHT;
+        assert!(
+            columns > 0 && rows > 0,
+            "pixel buffer is smaller than a glyph"
+        );
+
+        let mut console = TextConsole {
+            pixels,
+            columns,
+            rows,
+            column: 0,
+            row: 0,
+            foreground: DEFAULT_FOREGROUND,
+            background: DEFAULT_BACKGROUND,
+            bold: false,
+            state: State::Normal,
+            params: [0; MAX_PARAMS],
+            param: 0,
+        };
+        console.clear();
+        console
+    }
+
+    /// Returns the number of characters per row.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Returns the number of rows of characters.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the column and row of the cursor.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.column, self.row)
+    }
+
+    /// Returns the pixels the console draws onto.
+    pub fn pixels(&self) -> &PixelBuffer<'a> {
+        &self.pixels
+    }
+
+    /// Clears the screen with the background color and moves the cursor to
+    /// the top-left corner.
+    pub fn clear(&mut self) {
+        let (width, height) = (self.pixels.width(), self.pixels.height());
+        let background = PALETTE[self.background];
+        self.pixels.fill_rect(0, 0, width, height, background);
+        self.column = 0;
+        self.row = 0;
+    }
+
+    /// Writes the byte `byte`, which is either printed or interpreted as a
+    /// control character or part of an escape sequence. Bytes outside of the
+    /// ASCII range are printed as `?`, and unsupported control characters are
+    /// ignored.
+    pub fn write_byte(&mut self, byte: u8) {
+        match self.state {
+            State::Normal => self.write_normal(byte),
+            State::Escape => {
+                self.state = match byte {
+                    b'[' => {
+                        self.params = [0; MAX_PARAMS];
+                        self.param = 0;
+                        State::Csi
+                    }
+                    _ => State::Normal,
+                }
+            }
+            State::Csi => match byte {
+                b'0'..=b'9' => {
+                    if let Some(param) = self.params.get_mut(self.param) {
+                        *param = param
+                            .saturating_mul(10)
+                            .saturating_add(u16::from(byte - b'0'));
+                    }
+                }
+                b';' => self.param += 1,
+                0x40..=0x7E => {
+                    self.state = State::Normal;
+                    self.dispatch(byte);
+                }
+                // Intermediate and private parameter bytes.
+                0x20..=0x3F => {}
+                _ => self.state = State::Normal,
+            },
+        }
+    }
+
+    /// Writes each byte in `bytes` with `write_byte`.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_byte(byte);
+        }
+    }
+
+    /// Handles `byte` outside of an escape sequence.
+    fn write_normal(&mut self, byte: u8) {
+        match byte {
+            0x1B => self.state = State::Escape,
+            b'\n' => self.newline(),
+            b'\r' => self.column = 0,
+            b'\t' => {
+                self.column = (self.column / 8 + 1) * 8;
+                if self.column >= self.columns {
+                    self.newline();
+                }
+            }
+            0x08 => self.column = self.column.saturating_sub(1),
+            0x20..=0x7E => self.put_char(byte as char),
+            0x00..=0x1F | 0x7F => {}
+            _ => self.put_char('?'),
+        }
+    }
+
+    /// Returns parameter `i` of the current escape sequence, or `default` if
+    /// it's missing or 0.
+    fn param_or(&self, i: usize, default: usize) -> usize {
+        match self.params.get(i) {
+            Some(&param) if i <= self.param && param != 0 => param as usize,
+            _ => default,
+        }
+    }
+
+    /// Runs the escape sequence ending with `byte`.
+    fn dispatch(&mut self, byte: u8) {
+        match byte {
+            b'm' => {
+                for i in 0..(self.param + 1).min(MAX_PARAMS) {
+                    let param = self.params[i];
+                    self.select_graphic_rendition(param);
+                }
+            }
+            b'J' => {
+                let (column, row) = (self.column, self.row);
+                match self.params[0] {
+                    0 => {
+                        let columns = self.columns;
+                        self.clear_cells(column, row, columns - column.min(columns), 1);
+                        let rows = self.rows - row - 1;
+                        self.clear_cells(0, row + 1, columns, rows);
+                    }
+                    2 => {
+                        let (columns, rows) = (self.columns, self.rows);
+                        self.clear_cells(0, 0, columns, rows);
+                    }
+                    _ => {}
+                }
+            }
+            b'K' => {
+                let (column, row, columns) = (self.column, self.row, self.columns);
+                match self.params[0] {
+                    0 => self.clear_cells(column, row, columns - column.min(columns), 1),
+                    2 => self.clear_cells(0, row, columns, 1),
+                    _ => {}
+                }
+            }
+            b'H' | b'f' => {
+                self.row = (self.param_or(0, 1) - 1).min(self.rows - 1);
+                self.column = (self.param_or(1, 1) - 1).min(self.columns - 1);
+            }
+            b'A' => self.row = self.row.saturating_sub(self.param_or(0, 1)),
+            b'B' => self.row = (self.row + self.param_or(0, 1)).min(self.rows - 1),
+            b'C' => self.column = (self.column + self.param_or(0, 1)).min(self.columns - 1),
+            b'D' => self.column = self.column.saturating_sub(self.param_or(0, 1)),
+            _ => {}
+        }
+    }
+
+    /// Applies the graphic rendition parameter `param`.
+    fn select_graphic_rendition(&mut self, param: u16) {
+        match param {
+            0 => {
+                self.foreground = DEFAULT_FOREGROUND;
+                self.background = DEFAULT_BACKGROUND;
+                self.bold = false;
+            }
+            1 => self.bold = true,
+            22 => self.bold = false,
+            30..=37 => self.foreground = (param - 30) as usize,
+            39 => self.foreground = DEFAULT_FOREGROUND,
+            40..=47 => self.background = (param - 40) as usize,
+            49 => self.background = DEFAULT_BACKGROUND,
+            90..=97 => self.foreground = (param - 90) as usize + 8,
+            100..=107 => self.background = (param - 100) as usize + 8,
+            _ => {}
+        }
+    }
+
+    /// Returns the color characters are drawn with.
+    fn foreground(&self) -> Color {
+        if self.bold && self.foreground < 8 {
+            PALETTE[self.foreground + 8]
+        } else {
+            PALETTE[self.foreground]
+        }
+    }
+
+    /// Fills the `columns` by `rows` cells starting at (`column`, `row`) with
+    /// the background color.
+    fn clear_cells(&mut self, column: usize, row: usize, columns: usize, rows: usize) {
+        let background = PALETTE[self.background];
+        self.pixels.fill_rect(
+            column * font::WIDTH,
+            row * font::HEIGHT,
+            columns * font::WIDTH,
+            rows * font::HEIGHT,
+            background,
+        );
+    }
+
+    /// Draws `c` at the cursor and advances it, first moving to the next line
+    /// if the current one is full.
+    fn put_char(&mut self, c: char) {
+        if self.column >= self.columns {
+            self.newline();
+        }
+
+        let (foreground, background) = (self.foreground(), PALETTE[self.background]);
+        let (x, y) = (self.column * font::WIDTH, self.row * font::HEIGHT);
+        for (dy, &bits) in font::glyph(c).iter().enumerate() {
+            for dx in 0..font::WIDTH {
+                let color = match bits & (0x80 >> dx) {
+                    0 => background,
+                    _ => foreground,
+                };
+                self.pixels.set_pixel(x + dx, y + dy, color);
+            }
+        }
+        self.column += 1;
+    }
+
+    /// Moves the cursor to the start of the next line, scrolling if it's on
+    /// the last one.
+    fn newline(&mut self) {
+        self.column = 0;
+        if self.row + 1 < self.rows {
+            self.row += 1;
+        } else {
+            let background = PALETTE[self.background];
+            self.pixels.scroll_up(font::HEIGHT, background);
+        }
+    }
+}
+
+impl<'a> fmt::Write for TextConsole<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if c <= '\u{7f}' {
+                self.write_byte(c as u8);
+            } else {
+                self.put_char('?');
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,394 @@
+use common::mmio;
+use timer::{Alarm, Duration};
+use volatile::{ReadVolatile, Volatile};
+
+/// The base address of the core-local peripherals' registers, from page 7 of
+/// the Quad-A7 control documentation.
+const LOCAL_BASE: usize = 0x4000_0000;
+
+/// The address of the first core's timer interrupt control register.
+const LOCAL_INT_BASE: usize = LOCAL_BASE + 0x40;
+
+/// `CNTP_CTL_EL0`: enables the timer.
+const CTL_ENABLE: u64 = 1 << 0;
+
+/// `CNTP_CTL_EL0`: the timer condition is met. Read-only.
+const CTL_ISTATUS: u64 = 1 << 2;
+
+/// Accessors for the system registers used by this module. These only exist
+/// on AArch64; elsewhere, they're simulated by `mock`.
+#[cfg(target_arch = "aarch64")]
+mod sysreg {
+    macro_rules! sysreg {
+        ($(#[$attr:meta])* fn $read:ident = $name:tt) => (
+            $(#[$attr])*
+            #[inline(always)]
+            pub fn $read() -> u64 {
+                let value: u64;
+                unsafe { asm!(concat!("mrs $0, ", $name) : "=r"(value) ::: "volatile") }
+                value
+            }
+        );
+        ($(#[$attr:meta])* fn $read:ident, $write:ident = $name:tt) => (
+            sysreg!($(#[$attr])* fn $read = $name);
+
+            #[inline(always)]
+            pub fn $write(value: u64) {
+                unsafe { asm!(concat!("msr ", $name, ", $0") :: "r"(value) :: "volatile") }
+            }
+        );
+    }
+
+    sysreg! {
+        /// The counter's frequency, in Hz.
+        fn cntfrq = "CNTFRQ_EL0"
+    }
+
+    sysreg! {
+        /// The counter's value.
+        fn cntpct = "CNTPCT_EL0"
+    }
+
+    sysreg! {
+        /// The number of ticks until the physical timer's condition is met,
+        /// as a signed 32-bit value.
+        fn cntp_tval, set_cntp_tval = "CNTP_TVAL_EL0"
+    }
+
+    sysreg! {
+        /// The counter value at which the physical timer's condition is met.
+        fn cntp_cval, set_cntp_cval = "CNTP_CVAL_EL0"
+    }
+
+    sysreg! {
+        /// The physical timer's control register.
+        fn cntp_ctl, set_cntp_ctl = "CNTP_CTL_EL0"
+    }
+
+    sysreg! {
+        /// The multiprocessor affinity register.
+        fn mpidr = "MPIDR_EL1"
+    }
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+use self::mock::sysreg;
+
+/// A host-side simulation of the calling core's generic timer, for testing
+/// code that uses `GenericTimer` off the Raspberry Pi.
+///
+/// The simulated counter only moves when told to with `set_counter` or
+/// `advance`. The timer's condition is met once the counter reaches the
+/// compare value, as on hardware. Like `volatile::mock`, the simulation's
+/// state is local to the current thread.
+#[cfg(not(target_arch = "aarch64"))]
+pub mod mock {
+    use std::cell::Cell;
+
+    use super::{CTL_ENABLE, CTL_ISTATUS};
+
+    #[derive(Clone, Copy)]
+    struct Registers {
+        frequency: u64,
+        counter: u64,
+        compare: u64,
+        control: u64,
+        core: u64,
+    }
+
+    /// The registers' values at reset, with the Raspberry Pi 3's 19.2MHz
+    /// counter frequency.
+    const RESET: Registers = Registers {
+        frequency: 19_200_000,
+        counter: 0,
+        compare: 0,
+        control: 0,
+        core: 0,
+    };
+
+    thread_local! {
+        static REGISTERS: Cell<Registers> = Cell::new(RESET);
+    }
+
+    fn get() -> Registers {
+        REGISTERS.with(|r| r.get())
+    }
+
+    fn update<F: FnOnce(&mut Registers)>(f: F) {
+        REGISTERS.with(|r| {
+            let mut registers = r.get();
+            f(&mut registers);
+            r.set(registers);
+        })
+    }
+
+    /// Resets every simulated register.
+    pub fn reset() {
+        update(|r| *r = RESET);
+    }
+
+    /// Sets the counter frequency, in Hz.
+    pub fn set_frequency(hz: u64) {
+        update(|r| r.frequency = hz);
+    }
+
+    /// Sets the counter to `ticks`.
+    pub fn set_counter(ticks: u64) {
+        update(|r| r.counter = ticks);
+    }
+
+    /// Advances the counter by `ticks`.
+    pub fn advance(ticks: u64) {
+        update(|r| r.counter += ticks);
+    }
+
+    /// Sets the index of the calling core.
+    pub fn set_core_id(core: usize) {
+        update(|r| r.core = core as u64);
+    }
+
+    /// Returns the counter value at which the timer's condition is met.
+    pub fn compare() -> u64 {
+        get().compare
+    }
+
+    /// Returns `true` if the timer is enabled.
+    pub fn is_enabled() -> bool {
+        get().control & CTL_ENABLE != 0
+    }
+
+    /// The accessors `GenericTimer` uses in place of the real registers.
+    pub(super) mod sysreg {
+        use super::{get, update, CTL_ENABLE, CTL_ISTATUS};
+
+        pub fn cntfrq() -> u64 {
+            get().frequency
+        }
+
+        pub fn cntpct() -> u64 {
+            get().counter
+        }
+
+        /// Like the real register, only the low 32 bits are meaningful: they
+        /// hold the signed number of ticks until the compare value.
+        pub fn cntp_tval() -> u64 {
+            let r = get();
+            u64::from(r.compare.wrapping_sub(r.counter) as u32)
+        }
+
+        pub fn set_cntp_tval(value: u64) {
+            let ticks = value as u32 as i32 as i64 as u64;
+            update(|r| r.compare = r.counter.wrapping_add(ticks));
+        }
+
+        pub fn cntp_cval() -> u64 {
+            get().compare
+        }
+
+        pub fn set_cntp_cval(value: u64) {
+            update(|r| r.compare = value);
+        }
+
+        pub fn cntp_ctl() -> u64 {
+            let r = get();
+            let met = r.control & CTL_ENABLE != 0 && r.counter >= r.compare;
+            r.control | if met { CTL_ISTATUS } else { 0 }
+        }
+
+        /// `ISTATUS` is read-only, so writes to it are ignored.
+        pub fn set_cntp_ctl(value: u64) {
+            update(|r| r.control = value & !CTL_ISTATUS);
+        }
+
+        pub fn mpidr() -> u64 {
+            get().core
+        }
+    }
+}
+
+/// Returns the index of the core this code is running on.
+pub fn core_id() -> usize {
+    (sysreg::mpidr() & 0b11) as usize
+}
+
+/// One of the timers of each core that can interrupt that core.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerInterrupt {
+    /// The secure physical timer, `CNTPS`.
+    SecurePhysical = 0,
+    /// The non-secure physical timer, `CNTP`, driven by `GenericTimer`.
+    NonSecurePhysical = 1,
+    /// The hypervisor timer, `CNTHP`.
+    Hypervisor = 2,
+    /// The virtual timer, `CNTV`.
+    Virtual = 3,
+}
+
+register! {
+    /// A core's timer interrupt control register, which routes each of its
+    /// timers to its IRQ or FIQ. FIQ takes precedence over IRQ.
+    mod timer_control: u32 {
+        irq[4]: 0..1 => bool,
+        fiq[4]: 4..5 => bool,
+    }
+}
+
+register! {
+    /// A core's IRQ or FIQ source register, which shows what is interrupting
+    /// the core.
+    mod source: u32 {
+        timer[4]: 0..1 => bool,
+        mailbox[4]: 4..5 => bool,
+        gpu: 8..9 => bool,
+        pmu: 9..10 => bool,
+        axi: 10..11 => bool,
+        local_timer: 11..12 => bool,
+    }
+}
+
+register_block! {
+    #[allow(non_snake_case)]
+    struct Registers {
+        0x00 => TIMER_CONTROL: [timer_control::Register<Volatile<u32>>; 4],
+        0x10 => MAILBOX_CONTROL: [Volatile<u32>; 4],
+        0x20 => IRQ_SOURCE: [source::Register<ReadVolatile<u32>>; 4],
+        0x30 => FIQ_SOURCE: [source::Register<ReadVolatile<u32>>; 4],
+        0x40 => @END,
+    }
+}
+
+/// The core-local interrupt controller, which routes each core's timers to
+/// that core's IRQ. Interrupts from the generic timers don't go through
+/// `interrupt::Controller`.
+pub struct LocalController {
+    registers: &'static mut Registers,
+}
+
+impl LocalController {
+    /// Returns a new handle to the core-local interrupt controller.
+    pub fn new() -> LocalController {
+        LocalController {
+            registers: unsafe { mmio(LOCAL_INT_BASE) },
+        }
+    }
+
+    /// Routes the timer interrupt `int` of core `core` to that core's IRQ.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `core` isn't less than 4.
+    pub fn enable(&mut self, core: usize, int: TimerInterrupt) {
+        self.registers.TIMER_CONTROL[core].modify(|_, w| w.irq(int as usize, true));
+    }
+
+    /// Stops routing the timer interrupt `int` of core `core` to its IRQ.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `core` isn't less than 4.
+    pub fn disable(&mut self, core: usize, int: TimerInterrupt) {
+        self.registers.TIMER_CONTROL[core].modify(|_, w| w.irq(int as usize, false));
+    }
+
+    /// Returns `true` if the timer interrupt `int` is interrupting core
+    /// `core` through its IRQ.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `core` isn't less than 4.
+    pub fn is_pending(&self, core: usize, int: TimerInterrupt) -> bool {
+        self.registers.IRQ_SOURCE[core].read().timer(int as usize)
+    }
+}
+
+/// The calling core's ARM generic timer: its view of the system counter and
+/// its non-secure physical timer, `CNTP`.
+///
+/// Each core has its own timer, so a `GenericTimer` must only be used on the
+/// core that created it. To interrupt the core, the timer's interrupt must be
+/// routed with `LocalController::enable(core_id(), NonSecurePhysical)`.
+pub struct GenericTimer {
+    frequency: u64,
+}
+
+impl GenericTimer {
+    /// Returns a handle to the calling core's generic timer.
+    pub fn new() -> GenericTimer {
+        GenericTimer {
+            frequency: sysreg::cntfrq(),
+        }
+    }
+
+    /// Returns the frequency of the counter, in Hz.
+    pub fn frequency(&self) -> u64 {
+        self.frequency
+    }
+
+    /// Reads the counter and returns the number of ticks since it started.
+    pub fn read(&self) -> u64 {
+        sysreg::cntpct()
+    }
+
+    /// Returns the number of ticks left until the alarm goes off, which is
+    /// negative once it has.
+    pub fn remaining_ticks(&self) -> i32 {
+        sysreg::cntp_tval() as i32
+    }
+
+    /// Returns the time elapsed since the counter started.
+    pub fn elapsed(&self) -> Duration {
+        let ticks = self.read();
+        let nanos = (ticks % self.frequency) * 1_000_000_000 / self.frequency;
+        Duration::new(ticks / self.frequency, nanos as u32)
+    }
+
+    /// Returns the number of ticks in `duration`.
+    fn ticks(&self, duration: Duration) -> u64 {
+        let nanos = u64::from(duration.subsec_nanos()) * self.frequency / 1_000_000_000;
+        duration.as_secs() * self.frequency + nanos
+    }
+}
+
+impl Alarm for GenericTimer {
+    /// Arms the timer using `CNTP_TVAL_EL0`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `after` is longer than `i32::MAX` ticks, about 111 seconds at
+    /// the Raspberry Pi 3's 19.2MHz.
+    fn set_alarm(&mut self, after: Duration) {
+        let ticks = self.ticks(after);
+        assert!(
+            ticks <= i32::max_value() as u64,
+            "alarm is too far in the future"
+        );
+        sysreg::set_cntp_tval(ticks);
+        sysreg::set_cntp_ctl(CTL_ENABLE);
+    }
+
+    fn is_matched(&self) -> bool {
+        sysreg::cntp_ctl() & (CTL_ENABLE | CTL_ISTATUS) == CTL_ENABLE | CTL_ISTATUS
+    }
+
+    /// The generic timer has no match flag: its condition holds, and its
+    /// interrupt stays asserted, until the timer is re-armed. This method
+    /// disables the timer instead.
+    fn clear_match(&mut self) {
+        sysreg::set_cntp_ctl(0);
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `period` is shorter than one tick.
+    fn rearm(&mut self, period: Duration) {
+        let period = self.ticks(period);
+        assert!(period > 0, "period is shorter than a tick");
+        let last = sysreg::cntp_cval();
+        let periods = match sysreg::cntpct().checked_sub(last) {
+            Some(late) => late / period + 1,
+            None => 1,
+        };
+        sysreg::set_cntp_cval(last + periods * period);
+        sysreg::set_cntp_ctl(CTL_ENABLE);
+    }
+}
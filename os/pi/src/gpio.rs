@@ -1,49 +1,99 @@
 use core::marker::PhantomData;
 
-use common::{states, IO_BASE};
-use volatile::prelude::*;
-use volatile::{ReadVolatile, Reserved, Volatile, WriteVolatile};
-
-/// An alternative GPIO function.
-#[repr(u8)]
-pub enum Function {
-    Input = 0b000,
-    Output = 0b001,
-    Alt0 = 0b100,
-    Alt1 = 0b101,
-    Alt2 = 0b110,
-    Alt3 = 0b111,
-    Alt4 = 0b011,
-    Alt5 = 0b010,
+use common::{mmio, states, IO_BASE};
+use timer;
+use volatile::{ReadVolatile, Volatile, WriteVolatile};
+
+bitfield_enum! {
+    /// An alternative GPIO function.
+    #[repr(u8)]
+    pub enum Function: u32 {
+        Input = 0b000,
+        Output = 0b001,
+        Alt0 = 0b100,
+        Alt1 = 0b101,
+        Alt2 = 0b110,
+        Alt3 = 0b111,
+        Alt4 = 0b011,
+        Alt5 = 0b010,
+    }
+}
+
+bitfield_enum! {
+    /// The pull-up/pull-down resistor configuration of a GPIO pin.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Pull: u32 {
+        /// Neither resistor is enabled; the pin floats when undriven.
+        None = 0b00,
+        Down = 0b01,
+        Up = 0b10,
+    }
+}
+
+register! {
+    /// The pull-up/pull-down control register.
+    mod pud: u32 {
+        /// The configuration applied to pins when they are clocked through
+        /// `PUDCLK`.
+        control: 0..2 => Pull,
+    }
+}
+
+register! {
+    /// A function select register, covering ten pins.
+    mod fsel: u32 {
+        /// The function of the `i`th pin covered by this register.
+        function[10]: 0..3 => Function,
+    }
+}
+
+register! {
+    /// A register holding one bit for each of 32 pins.
+    mod pins: u32 {
+        /// The bit for the `i`th pin covered by this register.
+        pin[32]: 0..1 => bool,
+    }
 }
 
-#[repr(C)]
-#[allow(non_snake_case)]
-struct Registers {
-    FSEL: [Volatile<u32>; 6],
-    __r0: Reserved<u32>,
-    SET: [WriteVolatile<u32>; 2],
-    __r1: Reserved<u32>,
-    CLR: [WriteVolatile<u32>; 2],
-    __r2: Reserved<u32>,
-    LEV: [ReadVolatile<u32>; 2],
-    __r3: Reserved<u32>,
-    EDS: [Volatile<u32>; 2],
-    __r4: Reserved<u32>,
-    REN: [Volatile<u32>; 2],
-    __r5: Reserved<u32>,
-    FEN: [Volatile<u32>; 2],
-    __r6: Reserved<u32>,
-    HEN: [Volatile<u32>; 2],
-    __r7: Reserved<u32>,
-    LEN: [Volatile<u32>; 2],
-    __r8: Reserved<u32>,
-    AREN: [Volatile<u32>; 2],
-    __r9: Reserved<u32>,
-    AFEN: [Volatile<u32>; 2],
-    __r10: Reserved<u32>,
-    PUD: Volatile<u32>,
-    PUDCLK: [Volatile<u32>; 2],
+register_block! {
+    #[allow(non_snake_case)]
+    struct Registers {
+        0x00 => FSEL: [fsel::Register<Volatile<u32>>; 6],
+        0x1C => SET: [pins::Register<WriteVolatile<u32>>; 2],
+        0x28 => CLR: [pins::Register<WriteVolatile<u32>>; 2],
+        0x34 => LEV: [pins::Register<ReadVolatile<u32>>; 2],
+        0x40 => EDS: [pins::Register<Volatile<u32>>; 2],
+        0x4C => REN: [pins::Register<Volatile<u32>>; 2],
+        0x58 => FEN: [pins::Register<Volatile<u32>>; 2],
+        0x64 => HEN: [pins::Register<Volatile<u32>>; 2],
+        0x70 => LEN: [pins::Register<Volatile<u32>>; 2],
+        0x7C => AREN: [pins::Register<Volatile<u32>>; 2],
+        0x88 => AFEN: [pins::Register<Volatile<u32>>; 2],
+        0x94 => PUD: pud::Register<Volatile<u32>>,
+        0x98 => PUDCLK: [pins::Register<Volatile<u32>>; 2],
+        0xA0 => @END,
+    }
+}
+
+/// A condition that the GPIO event detection logic can watch for.
+///
+/// The synchronous detectors sample the pin with the system clock, so they
+/// ignore glitches shorter than a few clock cycles; the asynchronous ones
+/// don't, and can catch very short pulses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// A synchronous low-to-high transition.
+    RisingEdge,
+    /// A synchronous high-to-low transition.
+    FallingEdge,
+    /// The pin being high.
+    High,
+    /// The pin being low.
+    Low,
+    /// An asynchronous low-to-high transition.
+    AsyncRisingEdge,
+    /// An asynchronous high-to-low transition.
+    AsyncFallingEdge,
 }
 
 /// Possible states for a GPIO pin.
@@ -66,7 +116,12 @@ pub struct Gpio<T> {
 
 /// The base address of the `GPIO` registers.
 const GPIO_BASE: usize = IO_BASE + 0x200000;
-const GPIO_MASK: u32 = 0b111;
+
+/// How long to wait, in microseconds, for the pull-up/pull-down control
+/// signals to set up and hold. The BCM2837 requires 150 cycles, well under a
+/// microsecond, but the timer only counts whole microseconds: waiting for two
+/// ticks guarantees that at least one full microsecond has passed.
+const PUD_WAIT_US: u64 = 2;
 
 impl<T> Gpio<T> {
     /// Transitions `self` to state `S`, consuming `self` and returning a new
@@ -80,6 +135,25 @@ impl<T> Gpio<T> {
             _state: PhantomData,
         }
     }
+
+    /// Returns the index of the register covering this pin in a pair of
+    /// one-bit-per-pin registers, and the index of the pin's bit in it.
+    #[inline(always)]
+    fn bank(&self) -> (usize, usize) {
+        ((self.pin / 32) as usize, (self.pin % 32) as usize)
+    }
+
+    /// Applies the pull-up/pull-down configuration `pull` to this pin using
+    /// the sequence from page 101 of the BCM2837 documentation.
+    fn pull(&mut self, pull: Pull) {
+        let (index, bit) = self.bank();
+        self.registers.PUD.write(|w| w.control(pull));
+        timer::spin_sleep_us(PUD_WAIT_US);
+        self.registers.PUDCLK[index].write(|w| w.pin(bit, true));
+        timer::spin_sleep_us(PUD_WAIT_US);
+        self.registers.PUD.write(|w| w.control(Pull::None));
+        self.registers.PUDCLK[index].write(|w| w);
+    }
 }
 
 impl Gpio<Uninitialized> {
@@ -94,7 +168,7 @@ impl Gpio<Uninitialized> {
         }
 
         Gpio {
-            registers: unsafe { &mut *(GPIO_BASE as *mut Registers) },
+            registers: unsafe { mmio(GPIO_BASE) },
             pin: pin,
             _state: PhantomData,
         }
@@ -103,13 +177,8 @@ impl Gpio<Uninitialized> {
     /// Enables the alternative function `function` for `self`. Consumes self
     /// and returns a `Gpio` structure in the `Alt` state.
     pub fn into_alt(self, function: Function) -> Gpio<Alt> {
-        let shift = (self.pin % 10) * 3;
-        let offset = self.pin / 10;
-        {
-            let gpio_pin = &mut self.registers.FSEL[offset as usize];
-            let gpio_cur: u32 = gpio_pin.read();
-            gpio_pin.write(gpio_cur & !(GPIO_MASK << shift) | ((function as u32) << shift));
-        }
+        let (index, offset) = ((self.pin / 10) as usize, (self.pin % 10) as usize);
+        self.registers.FSEL[index].modify(|_, w| w.function(offset, function));
         Gpio {
             pin: self.pin,
             registers: self.registers,
@@ -128,17 +197,26 @@ impl Gpio<Uninitialized> {
     pub fn into_input(self) -> Gpio<Input> {
         self.into_alt(Function::Input).transition()
     }
+
+    /// Enables the pull-up or pull-down resistor of this pin, or disables
+    /// both, before the pin is configured. The configuration is kept across
+    /// function changes and resets.
+    pub fn set_pull(&mut self, pull: Pull) {
+        self.pull(pull)
+    }
 }
 
 impl Gpio<Output> {
     /// Sets (turns on) the pin.
     pub fn set(&mut self) {
-        self.registers.SET[(self.pin / 32) as usize].write(1 << self.pin);
+        let (index, bit) = self.bank();
+        self.registers.SET[index].write(|w| w.pin(bit, true));
     }
 
     /// Clears (turns off) the pin.
     pub fn clear(&mut self) {
-        self.registers.CLR[(self.pin / 32) as usize].write(1 << self.pin);
+        let (index, bit) = self.bank();
+        self.registers.CLR[index].write(|w| w.pin(bit, true));
     }
 }
 
@@ -146,6 +224,124 @@ impl Gpio<Input> {
     /// Reads the pin's value. Returns `true` if the level is high and `false`
     /// if the level is low.
     pub fn level(&mut self) -> bool {
-        self.registers.LEV[(self.pin / 32) as usize].has_mask(1 << self.pin)
+        let (index, bit) = self.bank();
+        self.registers.LEV[index].read().pin(bit)
+    }
+
+    /// Enables the pull-up or pull-down resistor of this pin, or disables
+    /// both, so that the pin reads a defined level when nothing drives it.
+    pub fn set_pull(&mut self, pull: Pull) {
+        self.pull(pull)
+    }
+
+    /// Returns the detect enable register for `event` covering this pin.
+    fn event_register(&mut self, event: Event) -> &mut pins::Register<Volatile<u32>> {
+        let (index, _) = self.bank();
+        let registers = &mut *self.registers;
+        match event {
+            Event::RisingEdge => &mut registers.REN[index],
+            Event::FallingEdge => &mut registers.FEN[index],
+            Event::High => &mut registers.HEN[index],
+            Event::Low => &mut registers.LEN[index],
+            Event::AsyncRisingEdge => &mut registers.AREN[index],
+            Event::AsyncFallingEdge => &mut registers.AFEN[index],
+        }
+    }
+
+    /// Starts detecting `event` on this pin. Once detected, the event is
+    /// reported by `event_detected` until cleared with `clear_event`.
+    pub fn enable_event(&mut self, event: Event) {
+        let (_, bit) = self.bank();
+        self.event_register(event).modify(|_, w| w.pin(bit, true));
+    }
+
+    /// Stops detecting `event` on this pin.
+    pub fn disable_event(&mut self, event: Event) {
+        let (_, bit) = self.bank();
+        self.event_register(event).modify(|_, w| w.pin(bit, false));
+    }
+
+    /// Returns `true` if one of the events enabled on this pin has been
+    /// detected since the status was last cleared.
+    pub fn event_detected(&mut self) -> bool {
+        let (index, bit) = self.bank();
+        self.registers.EDS[index].read().pin(bit)
+    }
+
+    /// Clears the event detected status of this pin. Status bits are cleared
+    /// by writing a `1`, so the status of other pins is left untouched.
+    ///
+    /// While a `High` or `Low` event is enabled, the status is set again for as
+    /// long as the pin stays at that level.
+    pub fn clear_event(&mut self) {
+        let (index, bit) = self.bank();
+        self.registers.EDS[index].write(|w| w.pin(bit, true));
+    }
+
+    /// Blocks until the pin's level has stayed the same for `stable_us`
+    /// microseconds and returns that level.
+    pub fn debounced_level(&mut self, stable_us: u64) -> bool {
+        let mut level = self.level();
+        let mut since = timer::current_time();
+        loop {
+            let now = timer::current_time();
+            let current = self.level();
+            if current != level {
+                level = current;
+                since = now;
+            } else if now - since >= stable_us {
+                return level;
+            }
+        }
+    }
+}
+
+/// A software debouncer for a GPIO input pin.
+///
+/// Mechanical switches bounce between levels for a few milliseconds when
+/// they're pressed or released. A `Debouncer` only reports a new level once
+/// the pin has held it for a given time. Unlike `Gpio::debounced_level`, it
+/// never blocks, so it can be polled from a loop that does other work.
+#[derive(Debug)]
+pub struct Debouncer {
+    /// The debounced level.
+    stable: bool,
+    /// The level of the pin when it was last sampled.
+    level: bool,
+    /// The time, in microseconds, at which the pin changed to `level`.
+    since: u64,
+    stable_us: u64,
+}
+
+impl Debouncer {
+    /// Returns a new `Debouncer` that starts at `level` and reports a new
+    /// level once the pin has held it for `stable_us` microseconds.
+    pub fn new(level: bool, stable_us: u64) -> Debouncer {
+        Debouncer {
+            stable: level,
+            level,
+            since: timer::current_time(),
+            stable_us,
+        }
+    }
+
+    /// Returns the debounced level.
+    pub fn level(&self) -> bool {
+        self.stable
+    }
+
+    /// Samples `pin`. Returns the new debounced level if it just changed, and
+    /// `None` otherwise.
+    pub fn poll(&mut self, pin: &mut Gpio<Input>) -> Option<bool> {
+        let now = timer::current_time();
+        let level = pin.level();
+        if level != self.level {
+            self.level = level;
+            self.since = now;
+        } else if level != self.stable && now - self.since >= self.stable_us {
+            self.stable = level;
+            return Some(level);
+        }
+        None
     }
 }
@@ -0,0 +1,193 @@
+use common::{mmio, IO_BASE};
+use volatile::{ReadVolatile, Volatile};
+
+/// The base address of the ARM interrupt controller's registers.
+const INT_BASE: usize = IO_BASE + 0xB200;
+
+/// An interrupt raised by a peripheral. The discriminant of each variant is
+/// the interrupt's number in the table on page 113 of the BCM2837
+/// documentation. Interrupts used by the GPU, such as system timer channels 0
+/// and 2, are left out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    /// System timer compare channel 1.
+    Timer1 = 1,
+    /// System timer compare channel 3.
+    Timer3 = 3,
+    Usb = 9,
+    /// The auxiliary peripherals: the mini UART and the SPI1/SPI2 masters.
+    Aux = 29,
+    I2cSpiSlave = 43,
+    Pwa0 = 45,
+    Pwa1 = 46,
+    Smi = 48,
+    /// GPIO bank 0, pins 0 to 27.
+    Gpio0 = 49,
+    /// GPIO bank 1, pins 28 to 45.
+    Gpio1 = 50,
+    /// GPIO bank 2, pins 46 to 53.
+    Gpio2 = 51,
+    /// Any of the GPIO pins.
+    Gpio3 = 52,
+    I2c = 53,
+    Spi = 54,
+    Pcm = 55,
+    /// The PL011 UART.
+    Uart = 57,
+}
+
+impl Interrupt {
+    /// Every interrupt, in ascending order of number.
+    pub const ALL: [Interrupt; 16] = [
+        Interrupt::Timer1,
+        Interrupt::Timer3,
+        Interrupt::Usb,
+        Interrupt::Aux,
+        Interrupt::I2cSpiSlave,
+        Interrupt::Pwa0,
+        Interrupt::Pwa1,
+        Interrupt::Smi,
+        Interrupt::Gpio0,
+        Interrupt::Gpio1,
+        Interrupt::Gpio2,
+        Interrupt::Gpio3,
+        Interrupt::I2c,
+        Interrupt::Spi,
+        Interrupt::Pcm,
+        Interrupt::Uart,
+    ];
+
+    /// Returns the index of the register covering this interrupt in a pair of
+    /// one-bit-per-interrupt registers, and the index of its bit in it.
+    #[inline(always)]
+    fn bank(self) -> (usize, usize) {
+        let number = self as usize;
+        (number / 32, number % 32)
+    }
+}
+
+/// An interrupt raised by the ARM-side peripherals, reported and enabled
+/// through the "basic" registers. The discriminant of each variant is the
+/// interrupt's bit in those registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BasicInterrupt {
+    /// The ARM timer.
+    ArmTimer = 0,
+    /// The ARM mailbox.
+    Mailbox = 1,
+    Doorbell0 = 2,
+    Doorbell1 = 3,
+    /// GPU core 0 halted.
+    GpuHalted0 = 4,
+    /// GPU core 1 halted.
+    GpuHalted1 = 5,
+    /// An illegal access of type 1.
+    IllegalAccess1 = 6,
+    /// An illegal access of type 0.
+    IllegalAccess0 = 7,
+}
+
+register! {
+    /// A pending, enable or disable register with one bit for each of 32
+    /// peripheral interrupts.
+    mod irqs: u32 {
+        irq[32]: 0..1 => bool,
+    }
+}
+
+register! {
+    /// The basic pending, enable or disable register.
+    mod basic: u32 {
+        irq[8]: 0..1 => bool,
+        /// One of the interrupts in the first pending register is pending.
+        /// This bit is only meaningful in the basic pending register.
+        pending1: 8..9 => bool,
+        /// One of the interrupts in the second pending register is pending.
+        /// This bit is only meaningful in the basic pending register.
+        pending2: 9..10 => bool,
+    }
+}
+
+register_block! {
+    #[allow(non_snake_case)]
+    struct Registers {
+        0x00 => BASIC_PENDING: basic::Register<ReadVolatile<u32>>,
+        0x04 => PENDING: [irqs::Register<ReadVolatile<u32>>; 2],
+        0x0C => FIQ_CONTROL: Volatile<u32>,
+        0x10 => ENABLE: [irqs::Register<Volatile<u32>>; 2],
+        0x18 => ENABLE_BASIC: basic::Register<Volatile<u32>>,
+        0x1C => DISABLE: [irqs::Register<Volatile<u32>>; 2],
+        0x24 => DISABLE_BASIC: basic::Register<Volatile<u32>>,
+        0x28 => @END,
+    }
+}
+
+/// An interrupt controller. Used to enable and disable interrupts as well as
+/// to check if an interrupt is pending.
+///
+/// Writing a `0` to the enable and disable registers has no effect, so each
+/// interrupt can be enabled or disabled without reading back the others.
+pub struct Controller {
+    registers: &'static mut Registers,
+}
+
+impl Controller {
+    /// Returns a new handle to the interrupt controller.
+    pub fn new() -> Controller {
+        Controller {
+            registers: unsafe { mmio(INT_BASE) },
+        }
+    }
+
+    /// Enables the interrupt `int`.
+    pub fn enable(&mut self, int: Interrupt) {
+        let (index, bit) = int.bank();
+        self.registers.ENABLE[index].write(|w| w.irq(bit, true));
+    }
+
+    /// Disables the interrupt `int`.
+    pub fn disable(&mut self, int: Interrupt) {
+        let (index, bit) = int.bank();
+        self.registers.DISABLE[index].write(|w| w.irq(bit, true));
+    }
+
+    /// Returns `true` if `int` is pending. This is independent of whether
+    /// `int` is enabled.
+    pub fn is_pending(&self, int: Interrupt) -> bool {
+        let (index, bit) = int.bank();
+        self.registers.PENDING[index].read().irq(bit)
+    }
+
+    /// Returns an iterator over the interrupts that are pending, in ascending
+    /// order of number. The pending registers are read once, when this method
+    /// is called.
+    pub fn pending(&self) -> impl Iterator<Item = Interrupt> {
+        let pending = [
+            self.registers.PENDING[0].read(),
+            self.registers.PENDING[1].read(),
+        ];
+        Interrupt::ALL.iter().cloned().filter(move |int| {
+            let (index, bit) = int.bank();
+            pending[index].irq(bit)
+        })
+    }
+
+    /// Enables the basic interrupt `int`.
+    pub fn enable_basic(&mut self, int: BasicInterrupt) {
+        self.registers
+            .ENABLE_BASIC
+            .write(|w| w.irq(int as usize, true));
+    }
+
+    /// Disables the basic interrupt `int`.
+    pub fn disable_basic(&mut self, int: BasicInterrupt) {
+        self.registers
+            .DISABLE_BASIC
+            .write(|w| w.irq(int as usize, true));
+    }
+
+    /// Returns `true` if the basic interrupt `int` is pending.
+    pub fn is_basic_pending(&self, int: BasicInterrupt) -> bool {
+        self.registers.BASIC_PENDING.read().irq(int as usize)
+    }
+}
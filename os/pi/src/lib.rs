@@ -5,15 +5,26 @@
 #![feature(repr_align)]
 #![feature(attr_literals)]
 #![feature(never_type)]
+#![feature(conservative_impl_trait)]
 #![feature(pointer_methods)]
+#![feature(macro_vis_matcher)]
+#![feature(dotdoteq_in_patterns)]
 #![cfg_attr(not(feature = "std"), no_std)]
 
 #[cfg(feature = "std")]
 extern crate core;
+#[macro_use]
 extern crate volatile;
 
 pub mod atags;
 pub mod common;
+pub mod framebuffer;
+#[cfg(any(target_arch = "aarch64", feature = "mock"))]
+pub mod generic_timer;
 pub mod gpio;
+pub mod interrupt;
+pub mod mailbox;
+#[cfg(all(test, feature = "mock"))]
+mod tests;
 pub mod timer;
 pub mod uart;
@@ -0,0 +1,636 @@
+use core::marker::PhantomData;
+use core::mem;
+use core::ptr;
+
+use common::{mmio, IO_BASE};
+use volatile::barrier;
+use volatile::prelude::*;
+use volatile::{ReadVolatile, Volatile, WriteVolatile};
+
+/// The base address of the mailbox registers.
+const MAILBOX_BASE: usize = IO_BASE + 0xB880;
+
+/// The size, in 32-bit words, of a property message's buffer.
+const MESSAGE_WORDS: usize = 256;
+
+/// The code of a property message that is a request.
+const REQUEST: u32 = 0;
+
+/// The code of a property message that was processed successfully.
+const RESPONSE_SUCCESS: u32 = 0x8000_0000;
+
+/// Set in a tag's request/response code once the tag has been answered. The
+/// other bits are the length of the response, in bytes.
+const TAG_RESPONSE: u32 = 0x8000_0000;
+
+register! {
+    /// A mailbox's status register.
+    mod status: u32 {
+        /// The mailbox holds no message.
+        empty: 30..31 => bool,
+        /// The mailbox can't accept another message.
+        full: 31..32 => bool,
+    }
+}
+
+register_block! {
+    #[allow(non_snake_case)]
+    struct Registers {
+        0x00 => READ: ReadVolatile<u32>,
+        0x10 => PEEK: ReadVolatile<u32>,
+        0x14 => SENDER: ReadVolatile<u32>,
+        0x18 => STATUS: status::Register<ReadVolatile<u32>>,
+        0x1C => CONFIG: Volatile<u32>,
+        0x20 => WRITE: WriteVolatile<u32>,
+        0x38 => WRITE_STATUS: status::Register<ReadVolatile<u32>>,
+        0x40 => @END,
+    }
+}
+
+/// A mailbox channel, which selects the VideoCore service a message is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Power = 0,
+    Framebuffer = 1,
+    VirtualUart = 2,
+    Vchiq = 3,
+    Leds = 4,
+    Buttons = 5,
+    TouchScreen = 6,
+    /// The property interface, from the ARM to the VideoCore.
+    Property = 8,
+}
+
+/// The mailboxes between the ARM and the VideoCore. Messages are read from
+/// mailbox 0 and written to mailbox 1.
+///
+/// A message is a 32-bit value whose low 4 bits are the channel it's sent
+/// on. For most channels, the other bits are the address of a buffer, which
+/// must therefore be 16-byte aligned.
+pub struct Mailbox {
+    registers: &'static mut Registers,
+}
+
+impl Mailbox {
+    /// Returns a new handle to the mailboxes.
+    pub fn new() -> Mailbox {
+        Mailbox {
+            registers: unsafe { mmio(MAILBOX_BASE) },
+        }
+    }
+
+    /// Sends `data` on `channel`. Blocks until there is room for the message.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the low 4 bits of `data` is set.
+    pub fn write(&mut self, channel: Channel, data: u32) {
+        assert!(
+            data & 0xF == 0,
+            "mailbox data must leave the low 4 bits clear"
+        );
+        while self.registers.WRITE_STATUS.read().full() {}
+        barrier::dmb();
+        self.registers.WRITE.write(data | channel as u32);
+    }
+
+    /// Receives the next message on `channel`, and returns its data with the
+    /// channel bits cleared. Blocks until there is such a message; messages
+    /// for other channels are discarded.
+    pub fn read(&mut self, channel: Channel) -> u32 {
+        loop {
+            while self.registers.STATUS.read().empty() {}
+            let message = self.registers.READ.read();
+            if message & 0xF == channel as u32 {
+                barrier::dmb();
+                return message & !0xF;
+            }
+        }
+    }
+
+    /// Sends `data` on `channel`, then waits for and returns the reply.
+    pub fn call(&mut self, channel: Channel, data: u32) -> u32 {
+        self.write(channel, data);
+        self.read(channel)
+    }
+}
+
+/// An error from the property interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// There is no room left in the message for the tag.
+    MessageFull,
+    /// The VideoCore couldn't parse the message.
+    RequestFailed,
+    /// The VideoCore didn't answer the tag, usually because it doesn't know
+    /// it.
+    Unanswered,
+    /// The VideoCore answered the tag with a response of the given length, in
+    /// bytes, which doesn't fit the response type.
+    ResponseLength(u32),
+    /// The device doesn't exist.
+    NoSuchDevice,
+}
+
+/// A property tag: a request to the VideoCore's property interface, and the
+/// type of its response. The tag's value is the request.
+///
+/// # Safety
+///
+/// Both `Self` and `Self::Response` must be `#[repr(C)]` and made only of
+/// integers, so that any bytes are a valid response.
+pub unsafe trait Tag: Copy {
+    /// The tag's identifier.
+    const ID: u32;
+
+    /// The VideoCore's response to the tag.
+    type Response: Copy;
+}
+
+/// A tag that was pushed to a `Message`. Used to read its response.
+#[derive(Debug)]
+pub struct Slot<T: Tag> {
+    /// The index of the tag's first word in the message.
+    offset: usize,
+    _tag: PhantomData<T>,
+}
+
+impl<T: Tag> Clone for Slot<T> {
+    fn clone(&self) -> Slot<T> {
+        Slot {
+            offset: self.offset,
+            _tag: PhantomData,
+        }
+    }
+}
+
+impl<T: Tag> Copy for Slot<T> {}
+
+/// The buffer of a property message, with the alignment the mailbox needs.
+#[repr(C, align(16))]
+struct Buffer([u32; MESSAGE_WORDS]);
+
+/// A message to the property interface, made of one or more tags.
+///
+/// Tags are added with `push`, which returns a `Slot` to read the tag's
+/// response from once the message has been sent with `send`.
+///
+/// ```rust,ignore
+/// let mut message = Message::new();
+/// let revision = message.push(GetBoardRevision)?;
+/// let memory = message.push(GetArmMemory)?;
+/// message.send()?;
+/// let (revision, memory) = (message.response(revision)?, message.response(memory)?);
+/// ```
+pub struct Message {
+    buffer: Buffer,
+    /// The number of words used by the header and the tags.
+    len: usize,
+}
+
+impl Message {
+    /// Returns a new message with no tags.
+    pub fn new() -> Message {
+        Message {
+            buffer: Buffer([0; MESSAGE_WORDS]),
+            len: 2,
+        }
+    }
+
+    /// Returns the message's buffer, as it is sent to the VideoCore and
+    /// overwritten with its response.
+    pub fn as_words(&self) -> &[u32] {
+        &self.buffer.0
+    }
+
+    /// Returns the message's buffer, mutably.
+    pub fn as_mut_words(&mut self) -> &mut [u32] {
+        &mut self.buffer.0
+    }
+
+    /// Adds `tag` to the message. Returns a `Slot` for the tag's response, or
+    /// `Error::MessageFull` if there is no room left for the tag.
+    pub fn push<T: Tag>(&mut self, tag: T) -> Result<Slot<T>, Error> {
+        let size = mem::size_of::<T>().max(mem::size_of::<T::Response>());
+        let value_words = (size + 3) / 4;
+        // Keep room for the end tag.
+        if self.len + 3 + value_words + 1 > MESSAGE_WORDS {
+            return Err(Error::MessageFull);
+        }
+
+        let offset = self.len;
+        let words = &mut self.buffer.0[offset..offset + 3 + value_words];
+        words[0] = T::ID;
+        words[1] = (value_words * 4) as u32;
+        words[2] = REQUEST;
+        for word in &mut words[3..] {
+            *word = 0;
+        }
+        unsafe {
+            ptr::write_unaligned(words[3..].as_mut_ptr() as *mut T, tag);
+        }
+
+        self.len += 3 + value_words;
+        Ok(Slot {
+            offset,
+            _tag: PhantomData,
+        })
+    }
+
+    /// Sends the message to the VideoCore and waits for its response.
+    ///
+    /// The buffer is passed by its ARM physical address, which the VideoCore
+    /// sees at the same bus address while the ARM's caches are disabled.
+    pub fn send(&mut self) -> Result<(), Error> {
+        let len = self.len;
+        let words = &mut self.buffer.0;
+        words[len] = 0;
+        words[0] = ((len + 1) * 4) as u32;
+        words[1] = REQUEST;
+
+        let address = words.as_ptr() as usize as u32;
+        Mailbox::new().call(Channel::Property, address);
+
+        match unsafe { ptr::read_volatile(&words[1]) } {
+            RESPONSE_SUCCESS => Ok(()),
+            _ => Err(Error::RequestFailed),
+        }
+    }
+
+    /// Returns the response to the tag in `slot`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slot` wasn't returned by this message's `push`.
+    pub fn response<T: Tag>(&self, slot: Slot<T>) -> Result<T::Response, Error> {
+        let words = &self.buffer.0[slot.offset..];
+        assert!(words[0] == T::ID, "slot is for another message");
+        let code = words[2];
+        if code & TAG_RESPONSE == 0 {
+            return Err(Error::Unanswered);
+        }
+
+        let len = code & !TAG_RESPONSE;
+        if (len as usize) < mem::size_of::<T::Response>() || len > words[1] {
+            return Err(Error::ResponseLength(len));
+        }
+
+        Ok(unsafe { ptr::read_unaligned(words[3..].as_ptr() as *const T::Response) })
+    }
+}
+
+/// Sends a message made of the single tag `tag` and returns its response.
+pub fn property<T: Tag>(tag: T) -> Result<T::Response, Error> {
+    let mut message = Message::new();
+    let slot = message.push(tag)?;
+    message.send()?;
+    message.response(slot)
+}
+
+/// Implements `Tag` for each `$tag` with identifier `$id` and response
+/// `$response`.
+macro tags($($tag:ty = $id:expr => $response:ty;)*) {
+    $(
+        unsafe impl Tag for $tag {
+            const ID: u32 = $id;
+            type Response = $response;
+        }
+    )*
+}
+
+/// Gets the board's model.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GetBoardModel;
+
+/// Gets the board's revision.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GetBoardRevision;
+
+/// Gets the board's MAC address, in network byte order.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GetMacAddress;
+
+/// Gets the board's serial number.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GetBoardSerial;
+
+/// Gets the region of memory given to the ARM.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GetArmMemory;
+
+/// Gets the region of memory given to the VideoCore.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GetVcMemory;
+
+/// Gets the power state of a device.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GetPowerState {
+    pub device: u32,
+}
+
+/// Sets the power state of a device.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SetPowerState {
+    pub device: u32,
+    /// Bit 0 turns the device on, and bit 1 waits for the device to be
+    /// stable before responding.
+    pub state: u32,
+}
+
+/// Gets the rate of a clock, in Hz.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GetClockRate {
+    pub clock: u32,
+}
+
+/// Gets the maximum rate of a clock, in Hz.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GetMaxClockRate {
+    pub clock: u32,
+}
+
+/// Sets the rate of a clock, in Hz.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SetClockRate {
+    pub clock: u32,
+    pub rate: u32,
+    /// Set to 1 to keep other clocks from being raised with turbo mode.
+    pub skip_turbo: u32,
+}
+
+/// Gets the SoC's temperature, in thousandths of a degree Celsius.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GetTemperature {
+    /// Must be 0.
+    pub id: u32,
+}
+
+/// Gets the temperature above which the SoC throttles its clocks, in
+/// thousandths of a degree Celsius.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GetMaxTemperature {
+    /// Must be 0.
+    pub id: u32,
+}
+
+/// Allocates the framebuffer, or returns the one already allocated.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct AllocateBuffer {
+    /// The alignment of the framebuffer, in bytes.
+    pub alignment: u32,
+}
+
+/// Sets the size of the display, in pixels.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SetPhysicalSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Sets the size of the framebuffer, in pixels, which may be larger than
+/// the display.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SetVirtualSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Sets the number of bits per pixel of the framebuffer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SetDepth {
+    pub bits_per_pixel: u32,
+}
+
+/// Sets the order of the color components of a pixel.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SetPixelOrder {
+    /// 0 for BGR, 1 for RGB.
+    pub order: u32,
+}
+
+/// Gets the number of bytes per row of the framebuffer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GetPitch;
+
+/// Sets the position of the display in the framebuffer, in pixels.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SetVirtualOffset {
+    pub x: u32,
+    pub y: u32,
+}
+
+/// A region of memory.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub base: u32,
+    pub size: u32,
+}
+
+/// The power state of a device.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerState {
+    pub device: u32,
+    /// Bit 0 is set if the device is on, and bit 1 if it doesn't exist.
+    pub state: u32,
+}
+
+/// The rate of a clock, in Hz.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockRate {
+    pub clock: u32,
+    pub rate: u32,
+}
+
+/// A size, in pixels.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Size {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A position, in pixels.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub x: u32,
+    pub y: u32,
+}
+
+/// A temperature, in thousandths of a degree Celsius.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Temperature {
+    pub id: u32,
+    pub value: u32,
+}
+
+tags! {
+    GetBoardModel = 0x0001_0001 => u32;
+    GetBoardRevision = 0x0001_0002 => u32;
+    GetMacAddress = 0x0001_0003 => [u8; 6];
+    GetBoardSerial = 0x0001_0004 => u64;
+    GetArmMemory = 0x0001_0005 => MemoryRegion;
+    GetVcMemory = 0x0001_0006 => MemoryRegion;
+    GetPowerState = 0x0002_0001 => PowerState;
+    SetPowerState = 0x0002_8001 => PowerState;
+    GetClockRate = 0x0003_0002 => ClockRate;
+    GetMaxClockRate = 0x0003_0004 => ClockRate;
+    SetClockRate = 0x0003_8002 => ClockRate;
+    GetTemperature = 0x0003_0006 => Temperature;
+    GetMaxTemperature = 0x0003_000A => Temperature;
+    AllocateBuffer = 0x0004_0001 => MemoryRegion;
+    GetPitch = 0x0004_0008 => u32;
+    SetPhysicalSize = 0x0004_8003 => Size;
+    SetVirtualSize = 0x0004_8004 => Size;
+    SetDepth = 0x0004_8005 => u32;
+    SetPixelOrder = 0x0004_8006 => u32;
+    SetVirtualOffset = 0x0004_8009 => Position;
+}
+
+/// A device whose power can be controlled through the property interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Device {
+    Sd = 0,
+    Uart0 = 1,
+    Uart1 = 2,
+    UsbHcd = 3,
+    I2c0 = 4,
+    I2c1 = 5,
+    I2c2 = 6,
+    Spi = 7,
+    Ccp2tx = 8,
+}
+
+/// A clock whose rate can be queried through the property interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clock {
+    Emmc = 1,
+    /// The PL011 UART's reference clock.
+    Uart = 2,
+    Arm = 3,
+    /// The VPU core clock, which also drives the mini UART.
+    Core = 4,
+    V3d = 5,
+    H264 = 6,
+    Isp = 7,
+    Sdram = 8,
+    Pixel = 9,
+    Pwm = 10,
+}
+
+/// Returns the board's model.
+pub fn board_model() -> Result<u32, Error> {
+    property(GetBoardModel)
+}
+
+/// Returns the board's revision code.
+pub fn board_revision() -> Result<u32, Error> {
+    property(GetBoardRevision)
+}
+
+/// Returns the board's MAC address.
+pub fn mac_address() -> Result<[u8; 6], Error> {
+    property(GetMacAddress)
+}
+
+/// Returns the board's serial number.
+pub fn board_serial() -> Result<u64, Error> {
+    property(GetBoardSerial)
+}
+
+/// Returns the region of memory given to the ARM.
+pub fn arm_memory() -> Result<MemoryRegion, Error> {
+    property(GetArmMemory)
+}
+
+/// Returns the region of memory given to the VideoCore.
+pub fn vc_memory() -> Result<MemoryRegion, Error> {
+    property(GetVcMemory)
+}
+
+/// Returns the current rate of `clock`, in Hz.
+pub fn clock_rate(clock: Clock) -> Result<u32, Error> {
+    property(GetClockRate {
+        clock: clock as u32,
+    })
+    .map(|r| r.rate)
+}
+
+/// Returns the maximum rate of `clock`, in Hz.
+pub fn max_clock_rate(clock: Clock) -> Result<u32, Error> {
+    property(GetMaxClockRate {
+        clock: clock as u32,
+    })
+    .map(|r| r.rate)
+}
+
+/// Sets the rate of `clock` to `rate` Hz, or the closest rate it supports,
+/// and returns the rate that was set.
+pub fn set_clock_rate(clock: Clock, rate: u32) -> Result<u32, Error> {
+    property(SetClockRate {
+        clock: clock as u32,
+        rate,
+        skip_turbo: 0,
+    })
+    .map(|r| r.rate)
+}
+
+/// Returns the SoC's temperature, in thousandths of a degree Celsius.
+pub fn temperature() -> Result<u32, Error> {
+    property(GetTemperature { id: 0 }).map(|t| t.value)
+}
+
+/// Returns the temperature above which the SoC throttles its clocks, in
+/// thousandths of a degree Celsius.
+pub fn max_temperature() -> Result<u32, Error> {
+    property(GetMaxTemperature { id: 0 }).map(|t| t.value)
+}
+
+/// Returns `true` if `state` is on, or `Error::NoSuchDevice` if it says the
+/// device doesn't exist.
+fn is_on(state: PowerState) -> Result<bool, Error> {
+    match state.state & 0b10 {
+        0 => Ok(state.state & 0b1 != 0),
+        _ => Err(Error::NoSuchDevice),
+    }
+}
+
+/// Returns `true` if `device` is powered on.
+pub fn power_state(device: Device) -> Result<bool, Error> {
+    is_on(property(GetPowerState {
+        device: device as u32,
+    })?)
+}
+
+/// Powers `device` on or off, waiting for it to be stable. Returns `true` if
+/// the device is on afterwards.
+pub fn set_power(device: Device, on: bool) -> Result<bool, Error> {
+    is_on(property(SetPowerState {
+        device: device as u32,
+        state: on as u32 | 0b10,
+    })?)
+}
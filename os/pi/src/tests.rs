@@ -0,0 +1,858 @@
+use core::fmt::Write;
+
+use volatile::mock::{self, Access};
+use volatile::VolatileSlice;
+
+use common::IO_BASE;
+use framebuffer::{font, Color, PixelBuffer, TextConsole};
+use generic_timer::{self, GenericTimer, LocalController, TimerInterrupt};
+use gpio::{Debouncer, Event, Function, Gpio, Pull};
+use interrupt::{BasicInterrupt, Controller, Interrupt};
+use mailbox::{
+    self, Channel as MailboxChannel, ClockRate, Error, GetBoardRevision, GetClockRate, Mailbox,
+    Message, SetPowerState,
+};
+use timer::{self, Alarm, Channel, Duration, Instant, Timer};
+use uart::{
+    DataSize, LineErrors, Loopback, MiniUart, MiniUartConfig, Parity, Pl011, Pl011Config,
+    SerialPort, StopBits, WordLength,
+};
+
+const GPIO_BASE: usize = IO_BASE + 0x200000;
+const GPFSEL1: usize = GPIO_BASE + 0x04;
+const GPFSEL5: usize = GPIO_BASE + 0x14;
+const GPSET1: usize = GPIO_BASE + 0x20;
+const GPCLR0: usize = GPIO_BASE + 0x28;
+const GPLEV1: usize = GPIO_BASE + 0x38;
+const GPEDS1: usize = GPIO_BASE + 0x44;
+const GPREN1: usize = GPIO_BASE + 0x50;
+const GPHEN1: usize = GPIO_BASE + 0x68;
+const GPPUD: usize = GPIO_BASE + 0x94;
+const GPPUDCLK1: usize = GPIO_BASE + 0x9C;
+
+const TIMER_CS: usize = IO_BASE + 0x3000;
+const TIMER_CLO: usize = IO_BASE + 0x3004;
+const TIMER_CHI: usize = IO_BASE + 0x3008;
+const TIMER_C1: usize = IO_BASE + 0x3010;
+const TIMER_C3: usize = IO_BASE + 0x3018;
+
+const IRQ_BASIC_PENDING: usize = IO_BASE + 0xB200;
+const IRQ_PENDING1: usize = IO_BASE + 0xB204;
+const IRQ_PENDING2: usize = IO_BASE + 0xB208;
+const ENABLE_IRQS2: usize = IO_BASE + 0xB214;
+const ENABLE_BASIC_IRQS: usize = IO_BASE + 0xB218;
+const DISABLE_IRQS1: usize = IO_BASE + 0xB21C;
+
+const MBOX_READ: usize = IO_BASE + 0xB880;
+const MBOX_STATUS: usize = IO_BASE + 0xB898;
+const MBOX_WRITE: usize = IO_BASE + 0xB8A0;
+const MBOX_WRITE_STATUS: usize = IO_BASE + 0xB8B8;
+
+const LOCAL_BASE: usize = 0x4000_0000;
+const CORE1_TIMER_CONTROL: usize = LOCAL_BASE + 0x44;
+const CORE2_IRQ_SOURCE: usize = LOCAL_BASE + 0x68;
+
+const AUX_ENABLES: usize = IO_BASE + 0x215004;
+const AUX_MU_IO: usize = IO_BASE + 0x215040;
+const AUX_MU_LCR: usize = IO_BASE + 0x21504C;
+const AUX_MU_LSR: usize = IO_BASE + 0x215054;
+const AUX_MU_CNTL: usize = IO_BASE + 0x215060;
+const AUX_MU_BAUD: usize = IO_BASE + 0x215068;
+
+const UART0_DR: usize = IO_BASE + 0x201000;
+const UART0_FR: usize = IO_BASE + 0x201018;
+const UART0_IBRD: usize = IO_BASE + 0x201024;
+const UART0_FBRD: usize = IO_BASE + 0x201028;
+const UART0_LCRH: usize = IO_BASE + 0x20102C;
+const UART0_CR: usize = IO_BASE + 0x201030;
+const UART0_IMSC: usize = IO_BASE + 0x201038;
+
+/// Maps the peripheral address space to fresh host memory.
+fn setup() {
+    mock::reset();
+    mock::map(IO_BASE, 0x100_0000);
+    generic_timer::mock::reset();
+}
+
+/// Returns the writes recorded since the last call to `mock::take_accesses`,
+/// as `(address, value)` pairs, and forgets every recorded access.
+fn take_writes() -> Vec<(usize, u64)> {
+    mock::take_accesses()
+        .into_iter()
+        .filter_map(|access| match access {
+            Access::Write { addr, value } => Some((addr, value)),
+            Access::Read { .. } => None,
+        })
+        .collect()
+}
+
+#[test]
+fn into_alt_sets_only_its_fsel_bits() {
+    setup();
+    mock::poke::<u32>(GPFSEL1, !0);
+    Gpio::new(14).into_alt(Function::Alt5);
+    // Pin 14 is FSEL1 bits 12..15; Alt5 is 0b010.
+    assert_eq!(
+        mock::writes_to(GPFSEL1),
+        [u64::from(!(0b111 << 12) | 0b010u32 << 12)]
+    );
+
+    mock::take_accesses();
+    mock::poke::<u32>(GPFSEL5, 0);
+    Gpio::new(53).into_output();
+    assert_eq!(
+        mock::take_accesses(),
+        [
+            Access::Read {
+                addr: GPFSEL5,
+                value: 0
+            },
+            Access::Write {
+                addr: GPFSEL5,
+                value: 0b001 << 9
+            },
+        ]
+    );
+}
+
+#[test]
+fn set_clear_and_level_use_the_pins_bank() {
+    setup();
+    let mut pin = Gpio::new(35).into_output();
+    pin.set();
+    assert_eq!(mock::writes_to(GPSET1), [1 << 3]);
+
+    let mut pin = Gpio::new(4).into_output();
+    pin.clear();
+    assert_eq!(mock::writes_to(GPCLR0), [1 << 4]);
+
+    let mut pin = Gpio::new(40).into_input();
+    mock::queue_read(GPLEV1, &[1 << 8, !(1 << 8)]);
+    assert!(pin.level());
+    assert!(!pin.level());
+}
+
+#[test]
+fn set_pull_runs_the_clocking_sequence() {
+    setup();
+    let mut pin = Gpio::new(42).into_input();
+    mock::take_accesses();
+    // Let the timer advance past each wait as soon as it starts.
+    mock::queue_read(TIMER_CLO, &[0, 100, 100, 200]);
+    pin.set_pull(Pull::Up);
+    assert_eq!(
+        take_writes(),
+        [
+            (GPPUD, 0b10),
+            (GPPUDCLK1, 1 << 10),
+            (GPPUD, 0),
+            (GPPUDCLK1, 0)
+        ]
+    );
+}
+
+#[test]
+fn events_use_their_detect_registers() {
+    setup();
+    mock::poke(GPREN1, 0b1u32);
+    let mut pin = Gpio::new(40).into_input();
+
+    pin.enable_event(Event::RisingEdge);
+    pin.enable_event(Event::High);
+    assert_eq!(mock::peek::<u32>(GPREN1), 0b1 | 1 << 8);
+    assert_eq!(mock::peek::<u32>(GPHEN1), 1 << 8);
+    pin.disable_event(Event::RisingEdge);
+    assert_eq!(mock::peek::<u32>(GPREN1), 0b1);
+
+    mock::queue_read(GPEDS1, &[0b1, 1 << 8]);
+    assert!(!pin.event_detected());
+    assert!(pin.event_detected());
+
+    pin.clear_event();
+    assert_eq!(mock::writes_to(GPEDS1), [1 << 8]);
+}
+
+#[test]
+fn debounced_level_waits_for_a_stable_level() {
+    setup();
+    let mut pin = Gpio::new(40).into_input();
+    mock::queue_read(GPLEV1, &[1 << 8, 0, 1 << 8, 1 << 8, 1 << 8, 1 << 8]);
+    mock::queue_read(TIMER_CLO, &[0, 100, 200, 300, 1199, 1200]);
+    assert!(pin.debounced_level(1000));
+
+    let clock_reads = mock::accesses()
+        .into_iter()
+        .filter(|access| match *access {
+            Access::Read { addr, .. } => addr == TIMER_CLO,
+            _ => false,
+        })
+        .count();
+    assert_eq!(clock_reads, 6);
+}
+
+#[test]
+fn debouncer_reports_each_change_once() {
+    setup();
+    let mut pin = Gpio::new(40).into_input();
+    let mut debouncer = Debouncer::new(false, 1000);
+
+    mock::queue_read(GPLEV1, &[1 << 8, 0, 1 << 8, 1 << 8, 1 << 8]);
+    mock::queue_read(TIMER_CLO, &[100, 200, 300, 1000, 1300]);
+    assert_eq!(debouncer.poll(&mut pin), None);
+    assert_eq!(debouncer.poll(&mut pin), None);
+    assert_eq!(debouncer.poll(&mut pin), None);
+    assert_eq!(debouncer.poll(&mut pin), None);
+    assert_eq!(debouncer.poll(&mut pin), Some(true));
+    assert!(debouncer.level());
+
+    mock::queue_read(GPLEV1, &[1 << 8]);
+    mock::queue_read(TIMER_CLO, &[5000]);
+    assert_eq!(debouncer.poll(&mut pin), None);
+}
+
+#[test]
+#[should_panic]
+fn gpio_rejects_invalid_pins() {
+    setup();
+    Gpio::new(54);
+}
+
+#[test]
+fn current_time_combines_both_halves() {
+    setup();
+    mock::poke::<u32>(TIMER_CLO, 0xDEAD_BEEF);
+    mock::poke::<u32>(TIMER_CHI, 0x12);
+    assert_eq!(timer::current_time(), 0x12_DEAD_BEEF);
+}
+
+#[test]
+fn instants_measure_durations() {
+    setup();
+    mock::poke::<u32>(TIMER_CLO, 1_500);
+    let start = Instant::now();
+    assert_eq!(start, Instant::from_micros(1_500));
+
+    let deadline = start + Duration::from_millis(2);
+    assert_eq!(deadline.as_micros(), 3_500);
+    assert_eq!(deadline - start, Duration::from_micros(2_000));
+    assert_eq!(start - deadline, Duration::from_micros(0));
+    assert_eq!(start.checked_sub(Duration::from_millis(2)), None);
+
+    mock::poke::<u32>(TIMER_CLO, 1_750);
+    assert_eq!(start.elapsed(), Duration::from_micros(250));
+}
+
+#[test]
+fn alarms_arm_their_compare_channel() {
+    setup();
+    let mut timer = Timer::new();
+    mock::poke::<u32>(TIMER_CLO, 0xFFFF_FF00);
+    timer.set_alarm(Channel::One, Duration::from_micros(0x200));
+    assert_eq!(mock::writes_to(TIMER_C1), [0x100]);
+
+    timer.set_alarm_at(Channel::Three, Instant::from_micros(0x1_0000_0040));
+    assert_eq!(mock::writes_to(TIMER_C3), [0x40]);
+    assert_eq!(mock::writes_to(TIMER_CS), [1 << 1, 1 << 3]);
+
+    mock::poke::<u32>(TIMER_CS, 1 << 3);
+    assert!(!timer.is_matched(Channel::One));
+    assert!(timer.is_matched(Channel::Three));
+
+    timer.rearm(Channel::Three, Duration::from_millis(1));
+    assert_eq!(mock::peek::<u32>(TIMER_C3), 0x40 + 1000);
+    assert_eq!(mock::writes_to(TIMER_CS), [1 << 1, 1 << 3, 1 << 3]);
+}
+
+#[test]
+fn alarms_clear_the_match_before_arming() {
+    setup();
+    let mut timer = Timer::new();
+    mock::poke::<u32>(TIMER_CLO, 100);
+    mock::take_accesses();
+    timer.set_alarm(Channel::One, Duration::from_micros(50));
+    timer.set_alarm_at(Channel::Three, Instant::from_micros(300));
+    timer.rearm(Channel::Three, Duration::from_micros(1000));
+    assert_eq!(
+        take_writes(),
+        [
+            (TIMER_CS, 1 << 1),
+            (TIMER_C1, 150),
+            (TIMER_CS, 1 << 3),
+            (TIMER_C3, 300),
+            (TIMER_CS, 1 << 3),
+            (TIMER_C3, 1300),
+        ]
+    );
+}
+
+#[test]
+fn rearm_skips_missed_periods() {
+    setup();
+    let mut timer = Timer::new();
+    mock::poke::<u32>(TIMER_C1, 1_000);
+
+    // Noticed in time: the next match is one period after the last.
+    mock::poke::<u32>(TIMER_CLO, 1_200);
+    timer.rearm(Channel::One, Duration::from_micros(1_000));
+    assert_eq!(mock::peek::<u32>(TIMER_C1), 2_000);
+
+    // Three and a half periods late: the missed matches are skipped.
+    mock::poke::<u32>(TIMER_CLO, 5_500);
+    timer.rearm(Channel::One, Duration::from_micros(1_000));
+    assert_eq!(mock::peek::<u32>(TIMER_C1), 6_000);
+
+    // Exactly on a missed deadline, which may already have gone by.
+    mock::poke::<u32>(TIMER_CLO, 8_000);
+    timer.rearm(Channel::One, Duration::from_micros(1_000));
+    assert_eq!(mock::peek::<u32>(TIMER_C1), 9_000);
+
+    // Late across the counter wrapping around.
+    mock::poke::<u32>(TIMER_C1, 0xFFFF_FF00);
+    mock::poke::<u32>(TIMER_CLO, 0x0000_0500);
+    timer.rearm(Channel::One, Duration::from_micros(0x400));
+    assert_eq!(mock::peek::<u32>(TIMER_C1), 0x0000_0700);
+}
+
+#[test]
+fn generic_timer_rearm_skips_missed_periods() {
+    use generic_timer::mock as gt;
+
+    setup();
+    gt::set_frequency(1_000_000);
+    let mut timer = GenericTimer::new();
+    timer.set_alarm(Duration::from_millis(1));
+    gt::set_counter(4_200);
+    timer.rearm(Duration::from_millis(1));
+    assert_eq!(gt::compare(), 5_000);
+    assert!(!timer.is_matched());
+}
+
+#[test]
+#[should_panic]
+fn alarms_must_fit_in_the_compare_register() {
+    setup();
+    Timer::new().set_alarm(Channel::One, Duration::from_secs(5_000));
+}
+
+#[test]
+fn interrupts_are_enabled_and_disabled_by_bit() {
+    setup();
+    let mut controller = Controller::new();
+    controller.enable(Interrupt::Uart);
+    controller.enable(Interrupt::Gpio0);
+    controller.disable(Interrupt::Timer1);
+    controller.enable_basic(BasicInterrupt::Mailbox);
+    assert_eq!(
+        mock::take_accesses(),
+        [
+            Access::Write {
+                addr: ENABLE_IRQS2,
+                value: 1 << 25
+            },
+            Access::Write {
+                addr: ENABLE_IRQS2,
+                value: 1 << 17
+            },
+            Access::Write {
+                addr: DISABLE_IRQS1,
+                value: 1 << 1
+            },
+            Access::Write {
+                addr: ENABLE_BASIC_IRQS,
+                value: 1 << 1
+            },
+        ]
+    );
+}
+
+#[test]
+fn pending_interrupts_are_reported() {
+    setup();
+    mock::poke(IRQ_PENDING1, 1u32 << 3 | 1 << 29 | 1 << 30);
+    mock::poke(IRQ_PENDING2, 1u32 << 25);
+    mock::poke(IRQ_BASIC_PENDING, 1u32 << 0 | 1 << 8 | 1 << 9);
+
+    let controller = Controller::new();
+    assert!(controller.is_pending(Interrupt::Timer3));
+    assert!(!controller.is_pending(Interrupt::Timer1));
+    assert!(controller.is_pending(Interrupt::Uart));
+    assert!(controller.is_basic_pending(BasicInterrupt::ArmTimer));
+    assert!(!controller.is_basic_pending(BasicInterrupt::Mailbox));
+
+    let mut pending = controller.pending();
+    assert_eq!(pending.next(), Some(Interrupt::Timer3));
+    assert_eq!(pending.next(), Some(Interrupt::Aux));
+    assert_eq!(pending.next(), Some(Interrupt::Uart));
+    assert_eq!(pending.next(), None);
+}
+
+#[test]
+fn core_timer_interrupts_are_routed_per_core() {
+    setup();
+    mock::map(LOCAL_BASE, 0x100);
+    mock::poke(CORE1_TIMER_CONTROL, 1u32 << 4);
+    let mut controller = LocalController::new();
+
+    controller.enable(1, TimerInterrupt::NonSecurePhysical);
+    assert_eq!(mock::peek::<u32>(CORE1_TIMER_CONTROL), 1 << 4 | 1 << 1);
+    controller.disable(1, TimerInterrupt::NonSecurePhysical);
+    assert_eq!(mock::peek::<u32>(CORE1_TIMER_CONTROL), 1 << 4);
+
+    mock::poke(CORE2_IRQ_SOURCE, 1u32 << 1 | 1 << 8);
+    assert!(controller.is_pending(2, TimerInterrupt::NonSecurePhysical));
+    assert!(!controller.is_pending(2, TimerInterrupt::Virtual));
+    assert!(!controller.is_pending(1, TimerInterrupt::NonSecurePhysical));
+}
+
+#[test]
+#[should_panic(expected = "is not mapped")]
+fn mapping_replaces_every_overlapping_region() {
+    setup();
+    mock::map(LOCAL_BASE, 0x100);
+    mock::poke(LOCAL_BASE, 7u32);
+    mock::map(LOCAL_BASE - 0x10, 0x20);
+    assert_eq!(mock::peek::<u32>(LOCAL_BASE), 0);
+    mock::peek::<u32>(CORE1_TIMER_CONTROL);
+}
+
+#[test]
+fn generic_timer_counts_and_raises_alarms() {
+    use generic_timer::mock as gt;
+
+    setup();
+    gt::set_frequency(1_000_000);
+    gt::set_counter(2_500_000);
+    gt::set_core_id(3);
+    assert_eq!(generic_timer::core_id(), 3);
+
+    let mut timer = GenericTimer::new();
+    assert_eq!(timer.frequency(), 1_000_000);
+    assert_eq!(timer.read(), 2_500_000);
+    assert_eq!(timer.elapsed(), Duration::from_millis(2_500));
+
+    timer.set_alarm(Duration::from_millis(2));
+    assert_eq!(gt::compare(), 2_502_000);
+    assert!(gt::is_enabled());
+    assert_eq!(timer.remaining_ticks(), 2_000);
+    assert!(!timer.is_matched());
+
+    gt::advance(2_500);
+    assert_eq!(timer.remaining_ticks(), -500);
+    assert!(timer.is_matched());
+    timer.rearm(Duration::from_millis(2));
+    assert_eq!(gt::compare(), 2_504_000);
+    assert!(!timer.is_matched());
+
+    gt::advance(2_000);
+    assert!(timer.is_matched());
+    timer.clear_match();
+    assert!(!gt::is_enabled());
+    assert!(!timer.is_matched());
+}
+
+#[test]
+#[should_panic]
+fn generic_timer_alarms_must_fit_in_tval() {
+    setup();
+    GenericTimer::new().set_alarm(Duration::from_secs(200));
+}
+
+#[test]
+fn mailbox_polls_its_status() {
+    setup();
+    let mut mailbox = Mailbox::new();
+    mock::queue_read(MBOX_WRITE_STATUS, &[1 << 31, 1 << 31, 0]);
+    mailbox.write(MailboxChannel::Property, 0x1230);
+    assert_eq!(mock::writes_to(MBOX_WRITE), [0x1238]);
+
+    // The message for another channel is dropped.
+    mock::queue_read(MBOX_STATUS, &[1 << 30, 0, 0]);
+    mock::queue_read(MBOX_READ, &[0x4561, 0x7898]);
+    assert_eq!(mailbox.read(MailboxChannel::Property), 0x7890);
+}
+
+#[test]
+fn messages_lay_out_their_tags() {
+    let mut message = Message::new();
+    assert_eq!(message.as_words().as_ptr() as usize % 16, 0);
+    message.push(GetBoardRevision).unwrap();
+    message
+        .push(SetPowerState {
+            device: 3,
+            state: 0b11,
+        })
+        .unwrap();
+    assert_eq!(
+        &message.as_words()[2..13],
+        [0x0001_0002, 4, 0, 0, 0x0002_8001, 8, 0, 3, 0b11, 0, 0]
+    );
+
+    while message.push(GetBoardRevision).is_ok() {}
+    assert_eq!(
+        message.push(GetBoardRevision).err(),
+        Some(Error::MessageFull)
+    );
+}
+
+#[test]
+fn message_responses_are_checked() {
+    let mut message = Message::new();
+    let revision = message.push(GetBoardRevision).unwrap();
+    let clock = message.push(GetClockRate { clock: 2 }).unwrap();
+    assert_eq!(message.response(revision).err(), Some(Error::Unanswered));
+
+    // Answer the tags as the VideoCore would.
+    {
+        let words = message.as_mut_words();
+        words[4] = 0x8000_0004;
+        words[5] = 0xA020D3;
+        words[8] = 0x8000_0004;
+    }
+    assert_eq!(message.response(revision), Ok(0xA020D3));
+    assert_eq!(
+        message.response(clock).err(),
+        Some(Error::ResponseLength(4))
+    );
+
+    {
+        let words = message.as_mut_words();
+        words[8] = 0x8000_0008;
+        words[10] = 48_000_000;
+    }
+    assert_eq!(
+        message.response(clock),
+        Ok(ClockRate {
+            clock: 2,
+            rate: 48_000_000
+        })
+    );
+}
+
+#[test]
+fn unprocessed_messages_fail() {
+    setup();
+    mock::queue_read(MBOX_READ, &[0x8]);
+    assert_eq!(mailbox::board_revision(), Err(Error::RequestFailed));
+
+    let writes = mock::writes_to(MBOX_WRITE);
+    assert_eq!(writes.len(), 1);
+    assert_eq!(writes[0] & 0xF, 0x8);
+}
+
+/// Returns `true` if the character cell at `column`, `row` of `console`
+/// shows `c` drawn in `foreground` on `background`.
+fn shows(
+    console: &TextConsole,
+    column: usize,
+    row: usize,
+    c: char,
+    colors: (Color, Color),
+) -> bool {
+    let (foreground, background) = colors;
+    let pixels = console.pixels();
+    font::glyph(c).iter().enumerate().all(|(dy, &bits)| {
+        (0..font::WIDTH).all(|dx| {
+            let expected = match bits & (0x80 >> dx) {
+                0 => background,
+                _ => foreground,
+            };
+            pixels.pixel(column * font::WIDTH + dx, row * font::HEIGHT + dy) == expected
+        })
+    })
+}
+
+const GREY: Color = Color(0xAAAAAA);
+const RED: Color = Color(0xAA0000);
+const BRIGHT_GREEN: Color = Color(0x55FF55);
+const BLUE: Color = Color(0x0000AA);
+
+#[test]
+fn pixel_buffers_fill_and_scroll() {
+    let mut memory = vec![0u32; 6 * 4];
+    {
+        let mut pixels = PixelBuffer::new(VolatileSlice::from(&mut memory[..]), 4, 4, 6);
+        pixels.fill_rect(1, 1, 10, 2, Color(7));
+        assert_eq!(pixels.pixel(0, 1), Color(0));
+        assert_eq!(pixels.pixel(3, 2), Color(7));
+        pixels.set_pixel(0, 3, Color(9));
+        pixels.scroll_up(1, Color(1));
+        assert_eq!(pixels.pixel(1, 0), Color(7));
+        assert_eq!(pixels.pixel(0, 2), Color(9));
+        assert_eq!(pixels.pixel(0, 3), Color(1));
+    }
+    // The padding at the end of each row is left alone.
+    assert_eq!(memory[4..6], [0, 0]);
+}
+
+#[test]
+fn text_console_draws_wraps_and_scrolls() {
+    let mut memory = vec![0xFFu32; 3 * font::WIDTH * 2 * font::HEIGHT];
+    let pixels = PixelBuffer::new(
+        VolatileSlice::from(&mut memory[..]),
+        3 * font::WIDTH,
+        2 * font::HEIGHT,
+        3 * font::WIDTH,
+    );
+    let mut console = TextConsole::new(pixels);
+    assert_eq!((console.columns(), console.rows()), (3, 2));
+
+    write!(console, "abcd").unwrap();
+    assert_eq!(console.cursor(), (1, 1));
+    assert!(shows(&console, 0, 0, 'a', (GREY, Color::BLACK)));
+    assert!(shows(&console, 2, 0, 'c', (GREY, Color::BLACK)));
+    assert!(shows(&console, 0, 1, 'd', (GREY, Color::BLACK)));
+
+    write!(console, "\nx\u{e9}").unwrap();
+    assert_eq!(console.cursor(), (2, 1));
+    assert!(shows(&console, 0, 0, 'd', (GREY, Color::BLACK)));
+    assert!(shows(&console, 0, 1, 'x', (GREY, Color::BLACK)));
+    assert!(shows(&console, 1, 1, '?', (GREY, Color::BLACK)));
+
+    write!(console, "\r\tz").unwrap();
+    assert!(shows(&console, 2, 0, ' ', (GREY, Color::BLACK)));
+}
+
+#[test]
+fn text_console_understands_ansi_colors() {
+    let mut memory = vec![0u32; 4 * font::WIDTH * 2 * font::HEIGHT];
+    let pixels = PixelBuffer::new(
+        VolatileSlice::from(&mut memory[..]),
+        4 * font::WIDTH,
+        2 * font::HEIGHT,
+        4 * font::WIDTH,
+    );
+    let mut console = TextConsole::new(pixels);
+
+    write!(console, "\x1b[31;44mr\x1b[1;32mg\x1b[0mn\x1b[99Xo").unwrap();
+    assert!(shows(&console, 0, 0, 'r', (RED, BLUE)));
+    assert!(shows(&console, 1, 0, 'g', (BRIGHT_GREEN, BLUE)));
+    assert!(shows(&console, 2, 0, 'n', (GREY, Color::BLACK)));
+    assert!(shows(&console, 3, 0, 'o', (GREY, Color::BLACK)));
+
+    write!(console, "\x1b[2;3H\x1b[41mq\x1b[1;1H\x1b[K").unwrap();
+    assert!(shows(&console, 2, 1, 'q', (GREY, RED)));
+    assert!(shows(&console, 0, 0, ' ', (GREY, RED)));
+    assert!(shows(&console, 3, 0, ' ', (GREY, RED)));
+    assert_eq!(console.cursor(), (0, 0));
+}
+
+#[test]
+fn mini_uart_initialization() {
+    setup();
+    MiniUart::new();
+    assert_eq!(mock::writes_to(AUX_ENABLES), [0b1]);
+    assert_eq!(mock::writes_to(AUX_MU_BAUD), [270]);
+    assert_eq!(mock::writes_to(AUX_MU_LCR), [0b11]);
+    assert_eq!(mock::writes_to(AUX_MU_CNTL), [0b11]);
+    // Pins 14 and 15 are switched to Alt5 (TXD1/RXD1).
+    assert_eq!(mock::peek::<u32>(GPFSEL1) >> 12, 0b010_010);
+}
+
+#[test]
+fn mini_uart_baud_rate_follows_the_clock() {
+    setup();
+    let uart = MiniUart::with_config(MiniUartConfig {
+        baud_rate: 921600,
+        data_size: DataSize::SevenBit,
+        ..MiniUartConfig::default()
+    })
+    .unwrap();
+    // 250MHz / (8 * 921600) = 33.9, rounded to 34.
+    assert_eq!(mock::writes_to(AUX_MU_BAUD), [33]);
+    assert_eq!(mock::writes_to(AUX_MU_LCR), [0b00]);
+    assert_eq!(uart.baud_rate(), 919117);
+    assert_eq!(uart.baud_error(921600), Some(-26));
+
+    setup();
+    let uart = MiniUart::with_config(MiniUartConfig {
+        clock: 400_000_000,
+        ..MiniUartConfig::default()
+    })
+    .unwrap();
+    assert_eq!(mock::writes_to(AUX_MU_BAUD), [433]);
+    assert_eq!(uart.baud_rate(), 115207);
+    assert_eq!(uart.baud_error(115200), Some(0));
+    assert_eq!(uart.baud_error(0), None);
+
+    setup();
+    let config = |baud_rate, clock| MiniUartConfig {
+        baud_rate,
+        clock,
+        ..MiniUartConfig::default()
+    };
+    assert!(MiniUart::with_config(config(0, 250_000_000)).is_err());
+    assert!(MiniUart::with_config(config(115200, 100)).is_err());
+    assert!(MiniUart::with_config(config(300, 250_000_000)).is_err());
+    assert!(mock::accesses().is_empty());
+}
+
+#[test]
+fn write_byte_waits_for_room() {
+    setup();
+    let mut uart = MiniUart::new();
+    mock::take_accesses();
+    mock::queue_read(AUX_MU_LSR, &[0, 0, 1 << 5]);
+    uart.write_byte(b'a');
+    assert_eq!(
+        mock::take_accesses(),
+        [
+            Access::Read {
+                addr: AUX_MU_LSR,
+                value: 0
+            },
+            Access::Read {
+                addr: AUX_MU_LSR,
+                value: 0
+            },
+            Access::Read {
+                addr: AUX_MU_LSR,
+                value: 1 << 5
+            },
+            Access::Write {
+                addr: AUX_MU_IO,
+                value: b'a' as u64
+            },
+        ]
+    );
+}
+
+#[test]
+fn wait_for_byte_times_out() {
+    setup();
+    let mut uart = MiniUart::new();
+    uart.set_read_timeout(1);
+    mock::queue_read(TIMER_CLO, &[0, 500, 2000]);
+    assert_eq!(uart.wait_for_byte(), Err(()));
+
+    mock::queue_read(TIMER_CLO, &[0, 500]);
+    mock::queue_read(AUX_MU_LSR, &[0b1]);
+    assert_eq!(uart.wait_for_byte(), Ok(()));
+}
+
+#[test]
+fn pl011_initialization() {
+    setup();
+    let uart = Pl011::new();
+    // 48MHz / (16 * 115200) = 26.04, or 26 + 3/64.
+    assert_eq!(mock::writes_to(UART0_IBRD), [26]);
+    assert_eq!(mock::writes_to(UART0_FBRD), [3]);
+    assert_eq!(uart.baud_rate(), 115176);
+    // FIFOs on, 8 data bits; written after the divisors.
+    assert_eq!(mock::writes_to(UART0_LCRH), [0, 0b111 << 4]);
+    assert_eq!(mock::writes_to(UART0_CR), [0, 0b11 << 8 | 1]);
+    // Pins 14 and 15 are switched to Alt0 (TXD0/RXD0).
+    assert_eq!(mock::peek::<u32>(GPFSEL1) >> 12, 0b100_100);
+
+    setup();
+    Pl011::with_config(Pl011Config {
+        baud_rate: 9600,
+        word_length: WordLength::Seven,
+        parity: Parity::Even,
+        stop_bits: StopBits::Two,
+        ..Pl011Config::default()
+    })
+    .unwrap();
+    assert_eq!(mock::writes_to(UART0_IBRD), [312]);
+    assert_eq!(mock::writes_to(UART0_FBRD), [32]);
+    assert_eq!(mock::writes_to(UART0_LCRH), [0, 0b101_1110]);
+}
+
+#[test]
+fn pl011_rejects_unreachable_baud_rates() {
+    setup();
+    let config = |baud_rate| Pl011Config {
+        baud_rate,
+        ..Pl011Config::default()
+    };
+    assert!(Pl011::with_config(config(0)).is_err());
+    assert!(Pl011::with_config(config(4_000_000)).is_err());
+    assert!(Pl011::with_config(config(10)).is_err());
+    assert!(mock::accesses().is_empty());
+}
+
+#[test]
+fn pl011_reports_receive_errors() {
+    setup();
+    let mut uart = Pl011::new();
+    mock::queue_read(UART0_FR, &[1 << 4, 0]);
+    mock::queue_read(UART0_DR, &[u64::from(b'a')]);
+    assert_eq!(uart.try_read_byte(), Ok(b'a'));
+
+    let framing = LineErrors {
+        framing: true,
+        ..LineErrors::default()
+    };
+    mock::queue_read(UART0_DR, &[1 << 8 | u64::from(b'b')]);
+    assert_eq!(uart.try_read_byte(), Err((b'b', framing)));
+
+    mock::queue_read(UART0_DR, &[1 << 11 | u64::from(b'c'), u64::from(b'd')]);
+    assert_eq!(uart.read_byte(), b'c');
+    assert_eq!(uart.read_byte(), b'd');
+    let errors = uart.take_errors();
+    assert!(errors.overrun && !errors.framing);
+    assert!(uart.take_errors().is_empty());
+
+    uart.set_rx_interrupt(true);
+    assert_eq!(mock::peek::<u32>(UART0_IMSC), 0b101 << 4);
+    uart.set_tx_interrupt(true);
+    uart.set_rx_interrupt(false);
+    assert_eq!(mock::peek::<u32>(UART0_IMSC), 0b010 << 4);
+}
+
+/// Writes `bytes` to `port`, then reads as many bytes as are ready.
+fn echo(port: &mut SerialPort, bytes: &[u8], buf: &mut [u8]) -> usize {
+    for &byte in bytes {
+        port.write_byte(byte);
+    }
+    port.flush();
+
+    let mut read = 0;
+    while read < buf.len() && port.wait_for_byte().is_ok() {
+        buf[read] = port.read_byte();
+        read += 1;
+    }
+    read
+}
+
+#[test]
+fn loopback_reads_back_its_writes() {
+    let mut port = Loopback::new();
+    let mut buf = [0; 8];
+    assert_eq!(echo(&mut port, b"hello", &mut buf), 5);
+    assert_eq!(&buf[..5], b"hello");
+    assert!(!port.has_byte());
+    assert_eq!(port.wait_for_byte(), Err(()));
+
+    // Bytes written past the capacity are dropped.
+    for i in 0..300 {
+        port.write_byte(i as u8);
+    }
+    let mut buf = [0; 300];
+    assert_eq!(echo(&mut port, b"", &mut buf), 256);
+    assert_eq!(buf[255], 255);
+}
+
+#[test]
+fn uarts_are_serial_ports() {
+    setup();
+    let mut uart = MiniUart::new();
+    SerialPort::set_read_timeout(&mut uart, 1);
+    // Room to write, then idle, then one byte to read, then nothing.
+    mock::poke::<u8>(AUX_MU_LSR, 1 << 6 | 1 << 5);
+    mock::queue_read(AUX_MU_LSR, &[1 << 5, 1 << 6, 0b1, 0b1]);
+    mock::queue_read(AUX_MU_IO, &[u64::from(b'k')]);
+    mock::queue_read(TIMER_CLO, &[0, 500, 0, 500, 2000]);
+    let mut buf = [0; 4];
+    assert_eq!(echo(&mut &mut uart, b"o", &mut buf), 1);
+    assert_eq!(buf[0], b'k');
+    assert_eq!(mock::writes_to(AUX_MU_IO), [u64::from(b'o')]);
+
+    let mut uart = Pl011::new();
+    uart.set_read_timeout(1);
+    mock::poke::<u32>(UART0_FR, 1 << 4);
+    mock::queue_read(UART0_FR, &[0, 0, 0]);
+    mock::queue_read(UART0_DR, &[u64::from(b'!')]);
+    mock::queue_read(TIMER_CLO, &[0, 500, 0, 500, 2000]);
+    assert_eq!(echo(&mut uart, b"", &mut buf), 1);
+    assert_eq!(buf[0], b'!');
+}
@@ -1,17 +1,224 @@
-use common::IO_BASE;
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+
+use common::{mmio, IO_BASE};
+use interrupt::Interrupt;
 use volatile::prelude::*;
 use volatile::{ReadVolatile, Volatile};
 
 /// The base address for the ARM system timer registers.
 const TIMER_REG_BASE: usize = IO_BASE + 0x3000;
 
-#[repr(C)]
-#[allow(non_snake_case)]
-struct Registers {
-    CS: Volatile<u32>,
-    CLO: ReadVolatile<u32>,
-    CHI: ReadVolatile<u32>,
-    COMPARE: [Volatile<u32>; 4],
+register! {
+    /// The control/status register, with one match flag for each compare
+    /// channel. Writing `true` to a flag clears it.
+    mod cs: u32 {
+        matched[4]: 0..1 => bool,
+    }
+}
+
+register_block! {
+    #[allow(non_snake_case)]
+    struct Registers {
+        0x00 => CS: cs::Register<Volatile<u32>>,
+        0x04 => CLO: ReadVolatile<u32>,
+        0x08 => CHI: ReadVolatile<u32>,
+        0x0C => COMPARE: [Volatile<u32>; 4],
+        0x1C => @END,
+    }
+}
+
+/// A span of time with nanosecond precision, mirroring the parts of
+/// `std::time::Duration` that the drivers need, which `core` doesn't provide.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Duration {
+    secs: u64,
+    nanos: u32,
+}
+
+impl Duration {
+    /// Returns a `Duration` of `secs` seconds and `nanos` nanoseconds. Whole
+    /// seconds in `nanos` are carried into the seconds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the carry overflows the seconds.
+    pub fn new(secs: u64, nanos: u32) -> Duration {
+        let secs = secs
+            .checked_add(u64::from(nanos / 1_000_000_000))
+            .expect("overflow in Duration::new");
+        Duration {
+            secs,
+            nanos: nanos % 1_000_000_000,
+        }
+    }
+
+    /// Returns a `Duration` of `secs` seconds.
+    pub fn from_secs(secs: u64) -> Duration {
+        Duration { secs, nanos: 0 }
+    }
+
+    /// Returns a `Duration` of `millis` milliseconds.
+    pub fn from_millis(millis: u64) -> Duration {
+        Duration {
+            secs: millis / 1_000,
+            nanos: (millis % 1_000) as u32 * 1_000_000,
+        }
+    }
+
+    /// Returns a `Duration` of `micros` microseconds.
+    pub fn from_micros(micros: u64) -> Duration {
+        Duration {
+            secs: micros / 1_000_000,
+            nanos: (micros % 1_000_000) as u32 * 1_000,
+        }
+    }
+
+    /// Returns the number of whole seconds in this duration.
+    pub fn as_secs(&self) -> u64 {
+        self.secs
+    }
+
+    /// Returns the fractional part of this duration in whole microseconds.
+    pub fn subsec_micros(&self) -> u32 {
+        self.nanos / 1_000
+    }
+
+    /// Returns the fractional part of this duration in nanoseconds.
+    pub fn subsec_nanos(&self) -> u32 {
+        self.nanos
+    }
+}
+
+/// Returns the number of whole microseconds in `duration`.
+fn micros(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros())
+}
+
+/// A point in time, measured by the system timer with microsecond precision.
+///
+/// Like `std::time::Instant`, an `Instant` is only meaningful when compared
+/// to another one: the difference between two `Instant`s is a `Duration`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(u64);
+
+impl Instant {
+    /// Returns the current time.
+    pub fn now() -> Instant {
+        Instant(current_time())
+    }
+
+    /// Returns the `Instant` at which the system timer reads `micros`.
+    pub fn from_micros(micros: u64) -> Instant {
+        Instant(micros)
+    }
+
+    /// Returns the value of the system timer at this `Instant`.
+    pub fn as_micros(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns the time elapsed from `earlier` to `self`, or a zero duration
+    /// if `earlier` is later than `self`.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        Duration::from_micros(self.0.saturating_sub(earlier.0))
+    }
+
+    /// Returns the time elapsed since `self`.
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+
+    /// Returns `self + duration`, or `None` if it can't be represented.
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        self.0.checked_add(micros(duration)).map(Instant)
+    }
+
+    /// Returns `self - duration`, or `None` if it can't be represented.
+    pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+        self.0.checked_sub(micros(duration)).map(Instant)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        self.checked_add(duration)
+            .expect("overflow when adding duration to instant")
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, duration: Duration) -> Instant {
+        self.checked_sub(duration)
+            .expect("overflow when subtracting duration from instant")
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+/// A compare channel of the system timer. Channels 0 and 2 are used by the
+/// GPU, so only channels 1 and 3 are available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    One = 1,
+    Three = 3,
+}
+
+impl Channel {
+    /// Returns the interrupt raised when this channel matches, which must be
+    /// enabled for the match to interrupt the CPU.
+    pub fn interrupt(self) -> Interrupt {
+        match self {
+            Channel::One => Interrupt::Timer1,
+            Channel::Three => Interrupt::Timer3,
+        }
+    }
+}
+
+/// A timer that can raise an alarm once, after a delay, or periodically.
+///
+/// This is implemented both by a compare channel of the system timer, as
+/// `CompareAlarm`, and by each core's generic timer, as
+/// `generic_timer::GenericTimer`, so the kernel can use either to drive its
+/// tick.
+pub trait Alarm {
+    /// Arms the alarm to go off `after` from now and clears its match flag.
+    fn set_alarm(&mut self, after: Duration);
+
+    /// Returns `true` if the alarm has gone off since its match flag was last
+    /// cleared.
+    fn is_matched(&self) -> bool;
+
+    /// Clears the match flag, which also acknowledges the alarm's interrupt.
+    fn clear_match(&mut self);
+
+    /// Re-arms the alarm to go off `period` after it was last due to, and
+    /// clears its match flag. Calling this method each time the alarm goes
+    /// off produces a periodic tick that doesn't drift. If that point has
+    /// already passed, the ticks that were missed are skipped: the alarm goes
+    /// off at the next multiple of `period` that is still in the future.
+    fn rearm(&mut self, period: Duration);
 }
 
 /// The Raspberry Pi ARM system timer.
@@ -23,7 +230,7 @@ impl Timer {
     /// Returns a new instance of `Timer`.
     pub fn new() -> Timer {
         Timer {
-            registers: unsafe { &mut *(TIMER_REG_BASE as *mut Registers) },
+            registers: unsafe { mmio(TIMER_REG_BASE) },
         }
     }
 
@@ -35,6 +242,120 @@ impl Timer {
         // shift the high bits (which are actually only 32-bits wide) down
         high << 32 | low
     }
+
+    /// Arms `channel` to match `after` from now and clears its match flag.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `after` is longer than `u32::MAX` microseconds, about 71
+    /// minutes.
+    pub fn set_alarm(&mut self, channel: Channel, after: Duration) {
+        let after = micros(after);
+        assert!(
+            after <= u64::from(u32::max_value()),
+            "alarm is too far in the future"
+        );
+        self.clear_match(channel);
+        let now = self.registers.CLO.read();
+        self.registers.COMPARE[channel as usize].write(now.wrapping_add(after as u32));
+    }
+
+    /// Arms `channel` to match at `deadline` and clears its match flag.
+    ///
+    /// Only the low 32 bits of the counter are compared, so the channel
+    /// matches every time they equal those of `deadline`: a deadline that has
+    /// already passed only matches once the counter wraps around, about 71
+    /// minutes later.
+    pub fn set_alarm_at(&mut self, channel: Channel, deadline: Instant) {
+        self.clear_match(channel);
+        self.registers.COMPARE[channel as usize].write(deadline.0 as u32);
+    }
+
+    /// Returns `true` if `channel` has matched since its match flag was last
+    /// cleared.
+    pub fn is_matched(&self, channel: Channel) -> bool {
+        self.registers.CS.read().matched(channel as usize)
+    }
+
+    /// Clears the match flag of `channel`, which also acknowledges its
+    /// interrupt.
+    pub fn clear_match(&mut self, channel: Channel) {
+        self.registers
+            .CS
+            .write(|w| w.matched(channel as usize, true));
+    }
+
+    /// Re-arms `channel` to match `period` after it last matched, and clears
+    /// its match flag. Calling this method each time `channel` matches
+    /// produces a periodic tick that doesn't drift.
+    ///
+    /// If the caller took longer than `period` to notice the match, that
+    /// point has already passed and would only match once the counter wraps
+    /// around. The missed matches are skipped instead: `channel` is armed to
+    /// match at the first multiple of `period` after its last match that is
+    /// still in the future.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is zero or longer than `i32::MAX` microseconds,
+    /// about 35 minutes.
+    pub fn rearm(&mut self, channel: Channel, period: Duration) {
+        let period = micros(period);
+        assert!(period > 0, "period is zero");
+        assert!(period <= i32::max_value() as u64, "period is too long");
+        self.clear_match(channel);
+        let last = self.registers.COMPARE[channel as usize].read();
+        let now = self.registers.CLO.read();
+        // The counter is less than half its range past `last` if `last` has
+        // already gone by, and just short of it if `last` is still to come.
+        let late = u64::from(now.wrapping_sub(last));
+        let periods = if late <= i32::max_value() as u64 {
+            late / period + 1
+        } else {
+            1
+        };
+        let next = last.wrapping_add((periods * period) as u32);
+        self.registers.COMPARE[channel as usize].write(next);
+    }
+}
+
+/// An `Alarm` driven by one compare channel of the system timer.
+pub struct CompareAlarm {
+    timer: Timer,
+    channel: Channel,
+}
+
+impl CompareAlarm {
+    /// Returns an `Alarm` driven by compare channel `channel`.
+    pub fn new(channel: Channel) -> CompareAlarm {
+        CompareAlarm {
+            timer: Timer::new(),
+            channel,
+        }
+    }
+
+    /// Returns the compare channel driving this alarm.
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+}
+
+impl Alarm for CompareAlarm {
+    fn set_alarm(&mut self, after: Duration) {
+        self.timer.set_alarm(self.channel, after)
+    }
+
+    fn is_matched(&self) -> bool {
+        self.timer.is_matched(self.channel)
+    }
+
+    fn clear_match(&mut self) {
+        self.timer.clear_match(self.channel)
+    }
+
+    fn rearm(&mut self, period: Duration) {
+        self.timer.rearm(self.channel, period)
+    }
 }
 
 /// Returns the current time in microseconds.
@@ -50,6 +371,11 @@ pub fn spin_sleep_us(us: u64) {
     while timer.read() < until {}
 }
 
+/// Spins until `duration` has passed.
+pub fn spin_sleep(duration: Duration) {
+    spin_sleep_us(micros(duration));
+}
+
 /// Spins until `ms` milliseconds have passed.
 pub fn spin_sleep_ms(ms: u64) {
     spin_sleep_us(ms * 1000);
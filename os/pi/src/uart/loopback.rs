@@ -0,0 +1,68 @@
+use super::SerialPort;
+
+/// The number of bytes a `Loopback` holds.
+const CAPACITY: usize = 256;
+
+/// An in-memory serial port whose writes are read back, for testing code
+/// that talks to a serial port without hardware.
+///
+/// Nothing else can write to a `Loopback`, so waiting for a byte would never
+/// end: `wait_for_byte` returns `Err(())` right away if no byte is ready,
+/// whether or not a read timeout is set, and `read_byte` panics.
+pub struct Loopback {
+    buffer: [u8; CAPACITY],
+    /// The index of the next byte to read.
+    start: usize,
+    /// The number of bytes ready to be read.
+    len: usize,
+}
+
+impl Loopback {
+    /// Returns a new, empty loopback port.
+    pub fn new() -> Loopback {
+        Loopback {
+            buffer: [0; CAPACITY],
+            start: 0,
+            len: 0,
+        }
+    }
+}
+
+impl SerialPort for Loopback {
+    /// Reads never wait, so the timeout is ignored.
+    fn set_read_timeout(&mut self, _milliseconds: u32) {}
+
+    /// Queues `byte` to be read. Like a UART whose receive FIFO is full, the
+    /// byte is lost if `CAPACITY` bytes are already queued.
+    fn write_byte(&mut self, byte: u8) {
+        if self.len < CAPACITY {
+            self.buffer[(self.start + self.len) % CAPACITY] = byte;
+            self.len += 1;
+        }
+    }
+
+    fn flush(&mut self) {}
+
+    fn has_byte(&self) -> bool {
+        self.len > 0
+    }
+
+    fn wait_for_byte(&self) -> Result<(), ()> {
+        if self.has_byte() {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Panics if no byte is ready to be read.
+    fn read_byte(&mut self) -> u8 {
+        assert!(self.has_byte(), "read from an empty loopback");
+        let byte = self.buffer[self.start];
+        self.start = (self.start + 1) % CAPACITY;
+        self.len -= 1;
+        byte
+    }
+}
@@ -0,0 +1,262 @@
+use core::fmt;
+
+use volatile::prelude::*;
+use volatile::{ReadVolatile, Volatile};
+
+use common::{mmio, IO_BASE};
+use gpio::{Function, Gpio};
+use mailbox::{self, Clock};
+use timer::Duration;
+
+/// The base address for the `MU` registers.
+const MU_REG_BASE: usize = IO_BASE + 0x215040;
+
+/// The address of the `AUXENB` register from page 9 of the BCM2837
+/// documentation.
+const AUX_ENABLES: usize = IO_BASE + 0x215004;
+
+/// The frequency of the core clock set by the firmware by default.
+const DEFAULT_CLOCK: u32 = 250_000_000;
+
+register! {
+    /// The `AUXENB` register, which enables the auxiliary peripherals.
+    mod aux_enables: u8 {
+        mini_uart: 0..1 => bool,
+        spi1: 1..2 => bool,
+        spi2: 2..3 => bool,
+    }
+}
+
+bitfield_enum! {
+    /// The size of a data word, as set in the `AUX_MU_LCR_REG` register.
+    #[repr(u8)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum DataSize: u8 {
+        SevenBit = 0b00,
+        EightBit = 0b11,
+    }
+}
+
+register! {
+    /// The `AUX_MU_LCR_REG` register, which controls the line data format.
+    mod lcr: u8 {
+        data_size: 0..2 => DataSize,
+        /// Pull the TX line low continuously to send a break condition.
+        send_break: 6..7 => bool,
+        /// Give access to the baud rate register through `IO` and `IER`.
+        dlab: 7..8 => bool,
+    }
+}
+
+register! {
+    /// The `AUX_MU_LSR_REG` register, which shows the data status.
+    mod lsr: u8 {
+        /// The receive FIFO holds at least one byte.
+        data_ready: 0..1 => bool,
+        /// A byte was lost because the receive FIFO was full.
+        rx_overrun: 1..2 => bool,
+        /// The transmit FIFO can accept at least one byte.
+        tx_empty: 5..6 => bool,
+        /// The transmit FIFO is empty and the transmitter is idle.
+        tx_idle: 6..7 => bool,
+    }
+}
+
+register! {
+    /// The `AUX_MU_CNTL_REG` register, which enables the receiver and
+    /// transmitter and controls flow control.
+    mod cntl: u8 {
+        rx_enable: 0..1 => bool,
+        tx_enable: 1..2 => bool,
+        rts_flow_control: 2..3 => bool,
+        cts_flow_control: 3..4 => bool,
+    }
+}
+
+register_block! {
+    #[allow(non_snake_case)]
+    struct Registers {
+        0x00 => IO: Volatile<u8>,
+        0x04 => IER: Volatile<u8>,
+        0x08 => IIR: Volatile<u8>,
+        0x0C => LCR: lcr::Register<Volatile<u8>>,
+        0x10 => MCR: Volatile<u8>,
+        0x14 => LSR: lsr::Register<ReadVolatile<u8>>,
+        0x18 => MSR: ReadVolatile<u8>,
+        0x20 => CNTL: cntl::Register<Volatile<u8>>,
+        0x24 => STAT: ReadVolatile<u32>,
+        0x28 => BAUD: Volatile<u16>,
+        0x2C => @END,
+    }
+}
+
+/// The line settings of a `MiniUart`. The default is 115200 baud with 8-bit
+/// data words, for the firmware's default 250MHz core clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MiniUartConfig {
+    pub baud_rate: u32,
+    /// The frequency of the core clock, which clocks the mini UART, in Hz.
+    pub clock: u32,
+    pub data_size: DataSize,
+}
+
+impl MiniUartConfig {
+    /// Returns the configuration for `baud_rate` baud and 8-bit data words at
+    /// the current core clock frequency, as reported by the firmware.
+    pub fn with_core_clock(baud_rate: u32) -> Result<MiniUartConfig, mailbox::Error> {
+        Ok(MiniUartConfig {
+            baud_rate,
+            clock: mailbox::clock_rate(Clock::Core)?,
+            ..MiniUartConfig::default()
+        })
+    }
+}
+
+impl Default for MiniUartConfig {
+    fn default() -> MiniUartConfig {
+        MiniUartConfig {
+            baud_rate: 115200,
+            clock: DEFAULT_CLOCK,
+            data_size: DataSize::EightBit,
+        }
+    }
+}
+
+/// The Raspberry Pi's "mini UART".
+///
+/// The mini UART is clocked by the core clock, so its baud rate changes with
+/// the core clock frequency. Set `core_freq` in `config.txt` to keep the
+/// frequency fixed.
+pub struct MiniUart {
+    registers: &'static mut Registers,
+    timeout: Option<Duration>,
+    /// The baud rate divisor, `BAUD + 1`.
+    divisor: u32,
+    clock: u32,
+}
+
+impl MiniUart {
+    /// Initializes the mini UART with the default configuration: 8-bit data
+    /// words at ~115200 baud (baud divider of 270).
+    ///
+    /// By default, reads will never time out. To set a read timeout, use
+    /// `set_read_timeout()`.
+    pub fn new() -> MiniUart {
+        MiniUart::with_config(MiniUartConfig::default()).expect("default config is valid")
+    }
+
+    /// Initializes the mini UART with the configuration `config` by enabling
+    /// it as an auxiliary peripheral, setting the data size and baud rate,
+    /// setting GPIO pins 14 and 15 to alternative function 5 (TXD1/RXD1), and
+    /// finally enabling the UART transmitter and receiver.
+    ///
+    /// Returns `Err(())` if the baud rate can't be reached from the core
+    /// clock, in which case the UART is left untouched.
+    pub fn with_config(config: MiniUartConfig) -> Result<MiniUart, ()> {
+        // baudrate = clock / (8 * (BAUD + 1)), with the divisor `BAUD + 1`
+        // rounded to the nearest integer.
+        let baud = u64::from(config.baud_rate) * 8;
+        if baud == 0 {
+            return Err(());
+        }
+        let divisor = (u64::from(config.clock) + baud / 2) / baud;
+        if divisor == 0 || divisor > 0x1_0000 {
+            return Err(());
+        }
+
+        let registers = unsafe {
+            // Enable the mini UART as an auxiliary device.
+            let aux_enables: &mut aux_enables::Register<Volatile<u8>> = mmio(AUX_ENABLES);
+            aux_enables.modify(|_, w| w.mini_uart(true));
+            mmio::<Registers>(MU_REG_BASE)
+        };
+        registers.BAUD.write((divisor - 1) as u16);
+
+        Gpio::new(14).into_alt(Function::Alt5);
+        Gpio::new(15).into_alt(Function::Alt5);
+
+        registers.LCR.write(|w| w.data_size(config.data_size));
+        registers.CNTL.write(|w| w.rx_enable(true).tx_enable(true));
+
+        Ok(MiniUart {
+            registers,
+            timeout: None,
+            divisor: divisor as u32,
+            clock: config.clock,
+        })
+    }
+
+    /// Returns the actual baud rate, which differs from the requested one by
+    /// the rounding of the divisor.
+    pub fn baud_rate(&self) -> u32 {
+        self.clock / (8 * self.divisor)
+    }
+
+    /// Returns the difference between the actual baud rate and `baud_rate`,
+    /// in hundredths of a percent of `baud_rate`, or `None` if `baud_rate` is
+    /// zero. Most receivers tolerate an error of a few percent.
+    pub fn baud_error(&self, baud_rate: u32) -> Option<i32> {
+        if baud_rate == 0 {
+            return None;
+        }
+        let actual = i64::from(self.baud_rate());
+        let requested = i64::from(baud_rate);
+        Some(((actual - requested) * 10_000 / requested) as i32)
+    }
+
+    /// Set the read timeout to `milliseconds` milliseconds.
+    pub fn set_read_timeout(&mut self, milliseconds: u32) {
+        self.timeout = Some(Duration::from_millis(u64::from(milliseconds)));
+    }
+
+    /// Write the byte `byte`. This method blocks until there is space available
+    /// in the output FIFO.
+    pub fn write_byte(&mut self, byte: u8) {
+        while !self.registers.LSR.read().tx_empty() {}
+        self.registers.IO.write(byte);
+    }
+
+    /// Blocks until every byte written has been sent.
+    pub fn flush(&mut self) {
+        while !self.registers.LSR.read().tx_idle() {}
+    }
+
+    /// Returns `true` if there is at least one byte ready to be read. If this
+    /// method returns `true`, a subsequent call to `read_byte` is guaranteed to
+    /// return immediately. This method does not block.
+    pub fn has_byte(&self) -> bool {
+        self.registers.LSR.read().data_ready()
+    }
+
+    /// Blocks until there is a byte ready to read. If a read timeout is set,
+    /// this method blocks for at most that amount of time. Otherwise, this
+    /// method blocks indefinitely until there is a byte to read.
+    ///
+    /// Returns `Ok(())` if a byte is ready to read. Returns `Err(())` if the
+    /// timeout expired while waiting for a byte to be ready. If this method
+    /// returns `Ok(())`, a subsequent call to `read_byte` is guaranteed to
+    /// return immediately.
+    pub fn wait_for_byte(&self) -> Result<(), ()> {
+        super::wait_until(self.timeout, || self.has_byte())
+    }
+
+    /// Reads a byte. Blocks indefinitely until a byte is ready to be read.
+    pub fn read_byte(&mut self) -> u8 {
+        while !self.has_byte() {}
+        self.registers.IO.read()
+    }
+}
+
+// FIXME: Implement `fmt::Write` for `MiniUart`. A b'\r' byte should be written
+// before writing any b'\n' byte.
+impl fmt::Write for MiniUart {
+    fn write_str(&mut self, s: &str) -> Result<(), fmt::Error> {
+        for &byte in s.as_bytes() {
+            if byte == b'\n' {
+                self.write_byte(b'\r');
+            }
+            self.write_byte(byte);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+mod loopback;
+mod mini;
+mod pl011;
+
+pub use self::loopback::Loopback;
+pub use self::mini::{DataSize, MiniUart, MiniUartConfig};
+pub use self::pl011::{FifoLevel, LineErrors, Parity, Pl011, Pl011Config, StopBits, WordLength};
+
+use timer::{Duration, Instant};
+
+/// A serial port that sends and receives bytes, such as a UART.
+pub trait SerialPort {
+    /// Set the read timeout to `milliseconds` milliseconds.
+    fn set_read_timeout(&mut self, milliseconds: u32);
+
+    /// Write the byte `byte`. This method blocks until there is room to.
+    fn write_byte(&mut self, byte: u8);
+
+    /// Blocks until every byte written has been sent.
+    fn flush(&mut self);
+
+    /// Returns `true` if there is at least one byte ready to be read. This
+    /// method does not block.
+    fn has_byte(&self) -> bool;
+
+    /// Blocks until there is a byte ready to read, for at most the read
+    /// timeout if one is set.
+    ///
+    /// Returns `Ok(())` if a byte is ready to read. Returns `Err(())` if the
+    /// timeout expired while waiting for a byte to be ready.
+    fn wait_for_byte(&self) -> Result<(), ()>;
+
+    /// Reads a byte. Blocks indefinitely until a byte is ready to be read.
+    fn read_byte(&mut self) -> u8;
+}
+
+macro serial_port_impls($($uart:ty),*) {
+    $(
+        impl SerialPort for $uart {
+            fn set_read_timeout(&mut self, milliseconds: u32) {
+                <$uart>::set_read_timeout(self, milliseconds)
+            }
+
+            fn write_byte(&mut self, byte: u8) {
+                <$uart>::write_byte(self, byte)
+            }
+
+            fn flush(&mut self) {
+                <$uart>::flush(self)
+            }
+
+            fn has_byte(&self) -> bool {
+                <$uart>::has_byte(self)
+            }
+
+            fn wait_for_byte(&self) -> Result<(), ()> {
+                <$uart>::wait_for_byte(self)
+            }
+
+            fn read_byte(&mut self) -> u8 {
+                <$uart>::read_byte(self)
+            }
+        }
+    )*
+}
+
+serial_port_impls!(MiniUart, Pl011);
+
+impl<'a, T: SerialPort + ?Sized> SerialPort for &'a mut T {
+    fn set_read_timeout(&mut self, milliseconds: u32) {
+        (**self).set_read_timeout(milliseconds)
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        (**self).write_byte(byte)
+    }
+
+    fn flush(&mut self) {
+        (**self).flush()
+    }
+
+    fn has_byte(&self) -> bool {
+        (**self).has_byte()
+    }
+
+    fn wait_for_byte(&self) -> Result<(), ()> {
+        (**self).wait_for_byte()
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        (**self).read_byte()
+    }
+}
+
+/// Spins until `ready` returns `true`, for at most `timeout` if it's set.
+/// Returns `Err(())` if the timeout expired first.
+fn wait_until<F: FnMut() -> bool>(timeout: Option<Duration>, mut ready: F) -> Result<(), ()> {
+    match timeout {
+        None => {
+            while !ready() {}
+            Ok(())
+        }
+        Some(timeout) => {
+            let deadline = Instant::now() + timeout;
+            while Instant::now() <= deadline {
+                if ready() {
+                    return Ok(());
+                }
+            }
+            Err(())
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+mod uart_io {
+    use super::{Loopback, MiniUart, Pl011, SerialPort};
+    use std::io;
+
+    // The `io::Read::read()` implementation respects the read timeout by
+    // waiting at most that time for the _first byte_. It doesn't wait for any
+    // additional bytes but reads as many bytes as are ready. If the read times
+    // out, an error of kind `TimedOut` is returned.
+    //
+    // The `io::Write::write()` method writes all of the requested bytes before
+    // returning.
+    macro io_impls($($uart:ty),*) {
+        $(
+            impl io::Read for $uart {
+                fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                    match self.wait_for_byte() {
+                        Ok(_) => {
+                            let mut i = 0;
+                            while self.has_byte() && i < buf.len() {
+                                buf[i] = self.read_byte();
+                                i += 1;
+                            }
+                            Ok(i)
+                        }
+                        Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "Read timed out")),
+                    }
+                }
+            }
+
+            impl io::Write for $uart {
+                fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                    for &b in buf {
+                        self.write_byte(b);
+                    }
+                    Ok(buf.len())
+                }
+
+                fn flush(&mut self) -> io::Result<()> {
+                    SerialPort::flush(self);
+                    Ok(())
+                }
+            }
+        )*
+    }
+
+    io_impls!(MiniUart, Pl011, Loopback);
+}
@@ -0,0 +1,408 @@
+use core::fmt;
+
+use volatile::prelude::*;
+use volatile::{ReadVolatile, Volatile, WriteVolatile};
+
+use common::{mmio, IO_BASE};
+use gpio::{Function, Gpio};
+use timer::Duration;
+
+/// The base address of the PL011 UART's registers, `UART0`.
+const UART0_BASE: usize = IO_BASE + 0x201000;
+
+/// The frequency of the UART reference clock, `UARTCLK`, set by the firmware.
+const DEFAULT_CLOCK: u32 = 48_000_000;
+
+bitfield_enum! {
+    /// The number of data bits in a frame.
+    #[repr(u8)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum WordLength: u32 {
+        Five = 0b00,
+        Six = 0b01,
+        Seven = 0b10,
+        Eight = 0b11,
+    }
+}
+
+bitfield_enum! {
+    /// A fill level of a FIFO, at which its interrupt is raised.
+    #[repr(u8)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum FifoLevel: u32 {
+        OneEighth = 0b000,
+        OneQuarter = 0b001,
+        OneHalf = 0b010,
+        ThreeQuarters = 0b011,
+        SevenEighths = 0b100,
+    }
+}
+
+/// The parity bit sent after the data bits of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+    None,
+    Odd,
+    Even,
+}
+
+/// The number of stop bits sent at the end of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopBits {
+    One,
+    Two,
+}
+
+register! {
+    /// The `UARTDR` data register. Reads return a received byte along with
+    /// the errors detected while receiving it.
+    mod dr: u32 {
+        data: 0..8 => u32,
+        framing_error: 8..9 => bool,
+        parity_error: 9..10 => bool,
+        break_error: 10..11 => bool,
+        overrun_error: 11..12 => bool,
+    }
+}
+
+register! {
+    /// The `UARTFR` flag register.
+    mod fr: u32 {
+        /// The UART is sending data, including from the transmit FIFO.
+        busy: 3..4 => bool,
+        rx_empty: 4..5 => bool,
+        tx_full: 5..6 => bool,
+        rx_full: 6..7 => bool,
+        tx_empty: 7..8 => bool,
+    }
+}
+
+register! {
+    /// The `UARTLCRH` line control register.
+    mod lcrh: u32 {
+        send_break: 0..1 => bool,
+        parity_enable: 1..2 => bool,
+        even_parity: 2..3 => bool,
+        two_stop_bits: 3..4 => bool,
+        fifo_enable: 4..5 => bool,
+        word_length: 5..7 => WordLength,
+        stick_parity: 7..8 => bool,
+    }
+}
+
+register! {
+    /// The `UARTCR` control register.
+    mod cr: u32 {
+        uart_enable: 0..1 => bool,
+        loopback_enable: 7..8 => bool,
+        tx_enable: 8..9 => bool,
+        rx_enable: 9..10 => bool,
+        rts: 11..12 => bool,
+        rts_flow_control: 14..15 => bool,
+        cts_flow_control: 15..16 => bool,
+    }
+}
+
+register! {
+    /// The `UARTIFLS` interrupt FIFO level select register.
+    mod ifls: u32 {
+        tx: 0..3 => FifoLevel,
+        rx: 3..6 => FifoLevel,
+    }
+}
+
+register! {
+    /// The layout shared by the `UARTIMSC`, `UARTRIS`, `UARTMIS` and
+    /// `UARTICR` interrupt registers.
+    mod int: u32 {
+        rx: 4..5 => bool,
+        tx: 5..6 => bool,
+        rx_timeout: 6..7 => bool,
+        framing_error: 7..8 => bool,
+        parity_error: 8..9 => bool,
+        break_error: 9..10 => bool,
+        overrun_error: 10..11 => bool,
+    }
+}
+
+register_block! {
+    #[allow(non_snake_case)]
+    struct Registers {
+        0x00 => DR: dr::Register<Volatile<u32>>,
+        0x04 => RSRECR: Volatile<u32>,
+        0x18 => FR: fr::Register<ReadVolatile<u32>>,
+        0x20 => ILPR: Volatile<u32>,
+        0x24 => IBRD: Volatile<u32>,
+        0x28 => FBRD: Volatile<u32>,
+        0x2C => LCRH: lcrh::Register<Volatile<u32>>,
+        0x30 => CR: cr::Register<Volatile<u32>>,
+        0x34 => IFLS: ifls::Register<Volatile<u32>>,
+        0x38 => IMSC: int::Register<Volatile<u32>>,
+        0x3C => RIS: int::Register<ReadVolatile<u32>>,
+        0x40 => MIS: int::Register<ReadVolatile<u32>>,
+        0x44 => ICR: int::Register<WriteVolatile<u32>>,
+        0x48 => DMACR: Volatile<u32>,
+        0x4C => @END,
+    }
+}
+
+/// The line settings of a `Pl011`. The default is 115200 baud, 8 data bits,
+/// no parity and one stop bit (8N1), with both FIFO interrupts raised at half
+/// full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pl011Config {
+    pub baud_rate: u32,
+    /// The frequency of the UART reference clock, in Hz.
+    pub clock: u32,
+    pub word_length: WordLength,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    /// The receive FIFO level at which the receive interrupt is raised.
+    pub rx_level: FifoLevel,
+    /// The transmit FIFO level at or below which the transmit interrupt is
+    /// raised.
+    pub tx_level: FifoLevel,
+}
+
+impl Default for Pl011Config {
+    fn default() -> Pl011Config {
+        Pl011Config {
+            baud_rate: 115200,
+            clock: DEFAULT_CLOCK,
+            word_length: WordLength::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            rx_level: FifoLevel::OneHalf,
+            tx_level: FifoLevel::OneHalf,
+        }
+    }
+}
+
+/// The errors detected while receiving bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LineErrors {
+    /// A byte had no valid stop bit.
+    pub framing: bool,
+    /// A byte's parity didn't match the configured parity.
+    pub parity: bool,
+    /// The line was held low for longer than a frame.
+    pub break_condition: bool,
+    /// A byte was lost because the receive FIFO was full.
+    pub overrun: bool,
+}
+
+impl LineErrors {
+    /// Returns `true` if no error was detected.
+    pub fn is_empty(&self) -> bool {
+        !(self.framing || self.parity || self.break_condition || self.overrun)
+    }
+
+    /// Adds the errors in `other` to `self`.
+    fn merge(&mut self, other: LineErrors) {
+        self.framing |= other.framing;
+        self.parity |= other.parity;
+        self.break_condition |= other.break_condition;
+        self.overrun |= other.overrun;
+    }
+}
+
+/// The Raspberry Pi's PL011 UART, `UART0`.
+///
+/// Unlike the mini UART, the PL011 has its own reference clock, so its baud
+/// rate doesn't change with the core clock. It also supports parity, two stop
+/// bits and 5 to 8 data bits, and reports receive errors.
+///
+/// On boards with Bluetooth, the firmware connects the PL011 to the Bluetooth
+/// module unless `dtoverlay=pi3-miniuart-bt` or `dtoverlay=pi3-disable-bt` is
+/// set in `config.txt`. Its interrupt is `Interrupt::Uart`.
+pub struct Pl011 {
+    registers: &'static mut Registers,
+    timeout: Option<Duration>,
+    /// The baud rate divisor, in 64ths.
+    divisor: u32,
+    clock: u32,
+    errors: LineErrors,
+}
+
+impl Pl011 {
+    /// Initializes the PL011 UART with the default configuration.
+    ///
+    /// By default, reads will never time out. To set a read timeout, use
+    /// `set_read_timeout()`.
+    pub fn new() -> Pl011 {
+        Pl011::with_config(Pl011Config::default()).expect("default config is valid")
+    }
+
+    /// Initializes the PL011 UART with the configuration `config`: programs
+    /// the line settings and baud rate, sets GPIO pins 14 and 15 to
+    /// alternative function 0 (TXD0/RXD0), and enables the FIFOs, the
+    /// transmitter and the receiver.
+    ///
+    /// Returns `Err(())` if the baud rate can't be reached from the reference
+    /// clock, in which case the UART is left untouched.
+    pub fn with_config(config: Pl011Config) -> Result<Pl011, ()> {
+        if config.baud_rate == 0 {
+            return Err(());
+        }
+
+        // The divisor is `clock / (16 * baud)` with a 6-bit fraction, rounded
+        // to the nearest 64th.
+        let baud = u64::from(config.baud_rate);
+        let divisor = (u64::from(config.clock) * 4 + baud / 2) / baud;
+        let integer = divisor >> 6;
+        if integer == 0 || integer > 0xFFFF {
+            return Err(());
+        }
+
+        let registers = unsafe { mmio::<Registers>(UART0_BASE) };
+
+        // Disable the UART, let it finish the current byte, then flush the
+        // transmit FIFO by disabling it.
+        registers.CR.write(|w| w);
+        while registers.FR.read().busy() {}
+        registers.LCRH.modify(|_, w| w.fifo_enable(false));
+
+        Gpio::new(14).into_alt(Function::Alt0);
+        Gpio::new(15).into_alt(Function::Alt0);
+
+        registers.ICR.write(|w| w.bits(0x7FF));
+        registers.IBRD.write(integer as u32);
+        registers.FBRD.write(divisor as u32 & 0x3F);
+        // `LCRH` must be written after the divisors to latch them.
+        registers.LCRH.write(|w| {
+            w.word_length(config.word_length)
+                .parity_enable(config.parity != Parity::None)
+                .even_parity(config.parity == Parity::Even)
+                .two_stop_bits(config.stop_bits == StopBits::Two)
+                .fifo_enable(true)
+        });
+        registers
+            .IFLS
+            .write(|w| w.rx(config.rx_level).tx(config.tx_level));
+        registers
+            .CR
+            .write(|w| w.uart_enable(true).tx_enable(true).rx_enable(true));
+
+        Ok(Pl011 {
+            registers,
+            timeout: None,
+            divisor: divisor as u32,
+            clock: config.clock,
+            errors: LineErrors::default(),
+        })
+    }
+
+    /// Returns the actual baud rate, which differs from the requested one by
+    /// the rounding of the divisor.
+    pub fn baud_rate(&self) -> u32 {
+        (u64::from(self.clock) * 4 / u64::from(self.divisor)) as u32
+    }
+
+    /// Set the read timeout to `milliseconds` milliseconds.
+    pub fn set_read_timeout(&mut self, milliseconds: u32) {
+        self.timeout = Some(Duration::from_millis(u64::from(milliseconds)));
+    }
+
+    /// Write the byte `byte`. This method blocks until there is space available
+    /// in the transmit FIFO.
+    pub fn write_byte(&mut self, byte: u8) {
+        while self.registers.FR.read().tx_full() {}
+        self.registers.DR.write(|w| w.data(u32::from(byte)));
+    }
+
+    /// Blocks until every byte written has been sent.
+    pub fn flush(&mut self) {
+        while self.registers.FR.read().busy() {}
+    }
+
+    /// Returns `true` if there is at least one byte ready to be read. If this
+    /// method returns `true`, a subsequent call to `read_byte` is guaranteed to
+    /// return immediately. This method does not block.
+    pub fn has_byte(&self) -> bool {
+        !self.registers.FR.read().rx_empty()
+    }
+
+    /// Blocks until there is a byte ready to read. If a read timeout is set,
+    /// this method blocks for at most that amount of time. Otherwise, this
+    /// method blocks indefinitely until there is a byte to read.
+    ///
+    /// Returns `Ok(())` if a byte is ready to read. Returns `Err(())` if the
+    /// timeout expired while waiting for a byte to be ready.
+    pub fn wait_for_byte(&self) -> Result<(), ()> {
+        super::wait_until(self.timeout, || self.has_byte())
+    }
+
+    /// Reads a byte. Blocks indefinitely until a byte is ready to be read.
+    ///
+    /// Errors detected while receiving the byte are returned by the next call
+    /// to `take_errors`.
+    pub fn read_byte(&mut self) -> u8 {
+        match self.try_read_byte() {
+            Ok(byte) => byte,
+            Err((byte, errors)) => {
+                self.errors.merge(errors);
+                byte
+            }
+        }
+    }
+
+    /// Reads a byte. Blocks indefinitely until a byte is ready to be read.
+    ///
+    /// Returns the byte along with the errors detected while receiving it, if
+    /// any. A break condition is received as a 0 byte.
+    pub fn try_read_byte(&mut self) -> Result<u8, (u8, LineErrors)> {
+        while !self.has_byte() {}
+        let dr = self.registers.DR.read();
+        let errors = LineErrors {
+            framing: dr.framing_error(),
+            parity: dr.parity_error(),
+            break_condition: dr.break_error(),
+            overrun: dr.overrun_error(),
+        };
+
+        let byte = dr.data() as u8;
+        if errors.is_empty() {
+            Ok(byte)
+        } else {
+            Err((byte, errors))
+        }
+    }
+
+    /// Returns the errors detected by `read_byte` since the last call, and
+    /// forgets them.
+    pub fn take_errors(&mut self) -> LineErrors {
+        ::core::mem::replace(&mut self.errors, LineErrors::default())
+    }
+
+    /// Enables or disables the receive interrupt, raised when the receive
+    /// FIFO reaches its level or holds bytes that haven't been read for 32
+    /// bit periods.
+    pub fn set_rx_interrupt(&mut self, enable: bool) {
+        self.registers
+            .IMSC
+            .modify(|_, w| w.rx(enable).rx_timeout(enable));
+    }
+
+    /// Enables or disables the transmit interrupt, raised when the transmit
+    /// FIFO drains to its level.
+    pub fn set_tx_interrupt(&mut self, enable: bool) {
+        self.registers.IMSC.modify(|_, w| w.tx(enable));
+    }
+
+    /// Clears every pending interrupt.
+    pub fn clear_interrupts(&mut self) {
+        self.registers.ICR.write(|w| w.bits(0x7FF));
+    }
+}
+
+impl fmt::Write for Pl011 {
+    fn write_str(&mut self, s: &str) -> Result<(), fmt::Error> {
+        for &byte in s.as_bytes() {
+            if byte == b'\n' {
+                self.write_byte(b'\r');
+            }
+            self.write_byte(byte);
+        }
+        Ok(())
+    }
+}
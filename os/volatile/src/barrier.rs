@@ -0,0 +1,54 @@
+//! Memory barriers for accesses to device memory.
+//!
+//! The BCM2837 doesn't guarantee that accesses to different peripherals
+//! complete in program order: data read from one peripheral may arrive after
+//! data read later from another. The peripheral documentation asks for a
+//! memory barrier before the first write to a peripheral and after the last
+//! read from it; `device` does exactly that around a closure. On targets other
+//! than AArch64, the barriers only prevent the compiler from reordering
+//! accesses.
+
+use core::sync::atomic::{compiler_fence, Ordering};
+
+/// Data memory barrier: memory accesses before the barrier are observed
+/// before memory accesses after it.
+#[inline(always)]
+pub fn dmb() {
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        asm!("dmb sy" ::: "memory" : "volatile")
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Data synchronization barrier: no instruction after the barrier executes
+/// until all memory accesses before it have completed.
+#[inline(always)]
+pub fn dsb() {
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        asm!("dsb sy" ::: "memory" : "volatile")
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Instruction synchronization barrier: flushes the pipeline so that
+/// instructions after the barrier see the effects of those before it.
+#[inline(always)]
+pub fn isb() {
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        asm!("isb" ::: "memory" : "volatile")
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Calls `f`, which accesses a single peripheral, between two data memory
+/// barriers, and returns its result.
+#[inline(always)]
+pub fn device<R, F: FnOnce() -> R>(f: F) -> R {
+    dmb();
+    let result = f();
+    dmb();
+    result
+}
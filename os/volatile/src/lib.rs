@@ -1,12 +1,34 @@
 #![feature(decl_macro)]
 #![feature(optin_builtin_traits)]
+#![feature(asm)]
+#![feature(pointer_methods)]
+#![feature(macro_vis_matcher)]
+#![feature(const_fn)]
 
 #![no_std]
 
+#[cfg(any(test, feature = "mock"))]
+#[macro_use]
+extern crate std;
+
 mod traits;
 mod macros;
+#[macro_use]
+mod register;
+mod slice;
+#[cfg(test)]
+mod tests;
+
+pub mod barrier;
+
+#[cfg(feature = "mock")]
+pub mod mock;
 
 pub use traits::*;
+pub use register::{FieldValue, Padded};
+pub use slice::*;
+#[doc(hidden)]
+pub use register::__private;
 use macros::*;
 
 /// Reexports all of the traits in this crate.
@@ -20,6 +42,8 @@ use macros::*;
 pub mod prelude {
 	#[doc(no_inline)]
     pub use super::{Readable, Writeable, ReadableWriteable, Wrapper};
+    #[doc(no_inline)]
+    pub use super::{ReadableSlice, WriteableSlice, SliceWrapper};
 }
 
 /// A wrapper type that enforces **read-only** _volatile_ accesses to a raw
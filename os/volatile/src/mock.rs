@@ -0,0 +1,220 @@
+//! A host-side simulator for memory-mapped I/O, for unit-testing drivers.
+//!
+//! With the `mock` feature enabled, every read and write done through this
+//! crate's wrapper types is routed through this module. Device memory is
+//! simulated by mapping a range of physical addresses to zeroed host memory
+//! with `map`, after which `translate` returns the host address at which a
+//! driver should find its registers. Accesses to mapped memory are then
+//! recorded, in order, and can be inspected with `accesses`. Reads return the
+//! value in host memory unless values were queued for the address with
+//! `queue_read`, in which case the next queued value is returned instead.
+//!
+//! Accesses to memory that was never mapped are performed as usual and aren't
+//! recorded. The simulator's state is local to the current thread, so tests
+//! running in parallel don't observe each other's accesses.
+//!
+//! Host memory handed out by `map` is never freed, not even by `reset` or by
+//! a later `map` of the same addresses: drivers hold `&'static mut`
+//! references to their registers, which may outlive the mapping. Such a
+//! driver keeps accessing its old, now unrecorded, memory.
+//!
+//! Values are converted to and from the `u64`s used by this module using the
+//! host's byte order, and registers may be at most 8 bytes wide.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::mem;
+use std::ptr;
+use std::vec::Vec;
+
+/// A single access to mapped device memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// A read of `value` from physical address `addr`.
+    Read { addr: usize, value: u64 },
+    /// A write of `value` to physical address `addr`.
+    Write { addr: usize, value: u64 },
+}
+
+/// A range of physical addresses backed by leaked host memory.
+struct Region {
+    phys: usize,
+    host: usize,
+    len: usize,
+}
+
+impl Region {
+    /// Returns `true` if the region shares any address with the `len` bytes
+    /// starting at `phys`.
+    fn overlaps(&self, phys: usize, len: usize) -> bool {
+        phys < self.phys + self.len && self.phys < phys + len
+    }
+}
+
+#[derive(Default)]
+struct State {
+    regions: Vec<Region>,
+    queued: HashMap<usize, VecDeque<u64>>,
+    accesses: Vec<Access>,
+}
+
+impl State {
+    /// Returns the physical address corresponding to host address `host`, if
+    /// it lies in a mapped region.
+    fn phys(&self, host: usize) -> Option<usize> {
+        self.regions
+            .iter()
+            .find(|r| host >= r.host && host < r.host + r.len)
+            .map(|r| r.phys + (host - r.host))
+    }
+}
+
+thread_local! {
+    static STATE: RefCell<State> = RefCell::new(State::default());
+}
+
+/// Maps the `len` bytes of physical memory starting at `phys` to zeroed host
+/// memory. Every mapping that overlaps any of those bytes is removed.
+pub fn map(phys: usize, len: usize) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.regions.retain(|r| !r.overlaps(phys, len));
+        let words = (len + mem::size_of::<u64>() - 1) / mem::size_of::<u64>();
+        let mut memory: Vec<u64> = vec![0; words];
+        let host = memory.as_mut_ptr() as usize;
+        mem::forget(memory);
+        state.regions.push(Region {
+            phys,
+            host,
+            len: words * mem::size_of::<u64>(),
+        });
+    })
+}
+
+/// Returns the host address at which physical address `phys` is simulated.
+///
+/// # Panics
+///
+/// Panics if `phys` hasn't been mapped with `map`.
+pub fn translate(phys: usize) -> usize {
+    STATE.with(|state| {
+        state
+            .borrow()
+            .regions
+            .iter()
+            .find(|r| phys >= r.phys && phys < r.phys + r.len)
+            .map(|r| r.host + (phys - r.phys))
+            .unwrap_or_else(|| panic!("mock: physical address {:#x} is not mapped", phys))
+    })
+}
+
+/// Unmaps all memory and forgets all queued values and recorded accesses.
+/// The host memory itself stays allocated.
+pub fn reset() {
+    STATE.with(|state| *state.borrow_mut() = State::default())
+}
+
+/// Queues `values` to be returned, in order, by the next reads of physical
+/// address `phys`, instead of the value in memory.
+pub fn queue_read(phys: usize, values: &[u64]) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.queued.entry(phys).or_insert_with(VecDeque::new).extend(values);
+    })
+}
+
+/// Returns every access recorded since the last call to `take_accesses` or
+/// `reset`, in order.
+pub fn accesses() -> Vec<Access> {
+    STATE.with(|state| state.borrow().accesses.clone())
+}
+
+/// Returns every access recorded since the last call to `take_accesses` or
+/// `reset`, in order, and forgets them.
+pub fn take_accesses() -> Vec<Access> {
+    STATE.with(|state| mem::replace(&mut state.borrow_mut().accesses, Vec::new()))
+}
+
+/// Returns the values written to physical address `phys`, in order.
+pub fn writes_to(phys: usize) -> Vec<u64> {
+    accesses()
+        .into_iter()
+        .filter_map(|access| match access {
+            Access::Write { addr, value } if addr == phys => Some(value),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Reads the `T` at physical address `phys` without recording an access.
+///
+/// # Panics
+///
+/// Panics if `phys` hasn't been mapped with `map`.
+pub fn peek<T: Copy>(phys: usize) -> T {
+    unsafe { ptr::read_unaligned(translate(phys) as *const T) }
+}
+
+/// Writes `value` to physical address `phys` without recording an access.
+/// Use this to set the value a device register holds.
+///
+/// # Panics
+///
+/// Panics if `phys` hasn't been mapped with `map`.
+pub fn poke<T: Copy>(phys: usize, value: T) {
+    unsafe { ptr::write_unaligned(translate(phys) as *mut T, value) }
+}
+
+/// Converts `value` to a `u64`.
+unsafe fn to_bits<T>(value: *const T) -> u64 {
+    assert!(mem::size_of::<T>() <= mem::size_of::<u64>());
+    let mut bits = 0u64;
+    ptr::copy_nonoverlapping(
+        value as *const u8,
+        &mut bits as *mut u64 as *mut u8,
+        mem::size_of::<T>(),
+    );
+    bits
+}
+
+/// Converts `bits` to a `T`.
+unsafe fn from_bits<T>(bits: u64) -> T {
+    assert!(mem::size_of::<T>() <= mem::size_of::<u64>());
+    ptr::read_unaligned(&bits as *const u64 as *const T)
+}
+
+/// Performs a volatile read of `src`, recording it if `src` is mapped.
+#[doc(hidden)]
+pub unsafe fn read_volatile<T>(src: *const T) -> T {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let addr = match state.phys(src as usize) {
+            Some(addr) => addr,
+            None => return ptr::read_volatile(src),
+        };
+        let value = match state.queued.get_mut(&addr).and_then(|q| q.pop_front()) {
+            Some(bits) => from_bits(bits),
+            None => ptr::read_volatile(src),
+        };
+        let value_bits = to_bits(&value);
+        state.accesses.push(Access::Read {
+            addr,
+            value: value_bits,
+        });
+        value
+    })
+}
+
+/// Performs a volatile write of `value` to `dst`, recording it if `dst` is
+/// mapped.
+#[doc(hidden)]
+pub unsafe fn write_volatile<T>(dst: *mut T, value: T) {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if let Some(addr) = state.phys(dst as usize) {
+            let bits = to_bits(&value);
+            state.accesses.push(Access::Write { addr, value: bits });
+        }
+        ptr::write_volatile(dst, value)
+    })
+}
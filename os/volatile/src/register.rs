@@ -0,0 +1,471 @@
+use core::ops::{Deref, DerefMut};
+
+/// Trait implemented by types that can be stored in a bitfield of a register
+/// whose raw type is `T`.
+///
+/// Implementations are provided for `bool` and for `T` itself. Enumerations
+/// declared with `bitfield_enum!` implement this trait as well.
+pub trait FieldValue<T>: Sized {
+    /// The type returned by a read of the field.
+    type Read;
+
+    /// Converts the bits of a field, already shifted down and masked, into a
+    /// value.
+    fn from_bits(bits: T) -> Self::Read;
+
+    /// Converts `self` into the bits of a field, not yet shifted into place.
+    fn into_bits(self) -> T;
+}
+
+macro_rules! field_value_impls {
+    ($($raw:ty),*) => ($(
+        impl FieldValue<$raw> for bool {
+            type Read = bool;
+            #[inline(always)] fn from_bits(bits: $raw) -> bool { bits != 0 }
+            #[inline(always)] fn into_bits(self) -> $raw { self as $raw }
+        }
+
+        impl FieldValue<$raw> for $raw {
+            type Read = $raw;
+            #[inline(always)] fn from_bits(bits: $raw) -> $raw { bits }
+            #[inline(always)] fn into_bits(self) -> $raw { self }
+        }
+    )*)
+}
+
+field_value_impls!(u8, u16, u32, u64);
+
+/// Declares an enumeration that can be stored in a register bitfield.
+///
+/// The enumeration is declared as written, along with its attributes, and
+/// implements `FieldValue<T>` where `T` is the raw type named after the colon.
+/// Every variant must be given an explicit discriminant. Reading a field of
+/// this type returns `None` if the field holds a value with no variant.
+///
+/// ```rust,ignore
+/// bitfield_enum! {
+///     /// The size of a UART data word.
+///     #[repr(u8)]
+///     pub enum DataSize: u8 {
+///         SevenBit = 0b00,
+///         EightBit = 0b11,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! bitfield_enum {
+    (
+        $(#[$attr:meta])*
+        $vis:vis enum $name:ident: $raw:ty {
+            $($(#[$vattr:meta])* $variant:ident = $value:expr),* $(,)*
+        }
+    ) => (
+        $(#[$attr])*
+        $vis enum $name {
+            $($(#[$vattr])* $variant = $value),*
+        }
+
+        impl $crate::FieldValue<$raw> for $name {
+            type Read = Option<$name>;
+
+            #[inline(always)]
+            fn from_bits(bits: $raw) -> Option<$name> {
+                $(if bits == $value { return Some($name::$variant); })*
+                None
+            }
+
+            #[inline(always)]
+            fn into_bits(self) -> $raw {
+                self as $raw
+            }
+        }
+    )
+}
+
+/// Declares a register together with its named bitfields.
+///
+/// The macro expands to a module named `$name` containing:
+///
+///   * `Register<A>`, a wrapper around one of this crate's wrapper types `A`
+///     of raw type `$raw` (for instance `Volatile<u8>`), with the same layout.
+///     Its `read()` method returns an `R`. Its `write(|w| ...)` method starts
+///     from a value with every bit clear, lets the closure set fields on a
+///     `W`, and writes the result. Its `modify(|r, w| ...)` method does the
+///     same starting from the register's current value. Each method is only
+///     available when `A` supports the corresponding access.
+///   * `R`, a read value with a getter `field()` for every field.
+///   * `W`, a value being written with a setter `field(value)` for every
+///     field. Setters return `&mut W` so that calls can be chained.
+///
+/// A field is declared as `name: lo..hi => Type`, where `lo..hi` is the range
+/// of bits it occupies and `Type` implements `FieldValue<$raw>`. A field
+/// declared as `name[n]: lo..hi => Type` is repeated `n` times, every
+/// `hi - lo` bits; its getter and setter take the index of the repetition as
+/// their first argument and panic if it is out of bounds. Setters ignore the
+/// bits of a value that don't fit in the field.
+///
+/// ```rust,ignore
+/// register! {
+///     /// The mini UART's line status register.
+///     mod lsr: u8 {
+///         /// The receive FIFO holds at least one byte.
+///         data_ready: 0..1 => bool,
+///         /// The transmit FIFO can accept at least one byte.
+///         tx_empty: 5..6 => bool,
+///     }
+/// }
+///
+/// struct Registers {
+///     LSR: lsr::Register<ReadVolatile<u8>>,
+/// }
+///
+/// while !registers.LSR.read().tx_empty() {}
+/// ```
+#[macro_export]
+macro_rules! register {
+    (
+        $(#[$attr:meta])*
+        $vis:vis mod $name:ident: $raw:ty {
+            $(
+                $(#[$fattr:meta])*
+                $field:ident $([$count:tt])*: $lo:tt..$hi:tt => $fty:ty
+            ),* $(,)*
+        }
+    ) => (
+        $(#[$attr])*
+        #[allow(dead_code)]
+        $vis mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            /// The register, wrapping a volatile wrapper type `A`.
+            #[repr(C)]
+            pub struct Register<A>(A);
+
+            impl<A: $crate::Readable<$raw>> Register<A> {
+                /// Reads the register.
+                #[inline(always)]
+                pub fn read(&self) -> R {
+                    R($crate::Readable::read(&self.0))
+                }
+            }
+
+            impl<A: $crate::Writeable<$raw>> Register<A> {
+                /// Writes the value built by `f`, starting from a value with
+                /// every bit clear.
+                #[inline(always)]
+                pub fn write<F: FnOnce(&mut W) -> &mut W>(&mut self, f: F) {
+                    let mut w = W(0);
+                    f(&mut w);
+                    $crate::Writeable::write(&mut self.0, w.0);
+                }
+            }
+
+            impl<A: $crate::ReadableWriteable<$raw>> Register<A> {
+                /// Writes the value built by `f`, starting from the current
+                /// value of the register, which is also passed to `f`.
+                #[inline(always)]
+                pub fn modify<F: for<'w> FnOnce(&R, &'w mut W) -> &'w mut W>(&mut self, f: F) {
+                    let r = self.read();
+                    let mut w = W(r.0);
+                    f(&r, &mut w);
+                    $crate::Writeable::write(&mut self.0, w.0);
+                }
+            }
+
+            /// A value read from the register.
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            pub struct R($raw);
+
+            impl R {
+                /// Returns the raw bits of the value.
+                #[inline(always)]
+                pub fn bits(&self) -> $raw {
+                    self.0
+                }
+
+                $(register!(@get $raw, $(#[$fattr])* $field $([$count])*: $lo..$hi => $fty);)*
+            }
+
+            /// A value to be written to the register.
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            pub struct W($raw);
+
+            impl W {
+                /// Overwrites the raw bits of the value.
+                #[inline(always)]
+                pub fn bits(&mut self, bits: $raw) -> &mut W {
+                    self.0 = bits;
+                    self
+                }
+
+                $(register!(@set $raw, $(#[$fattr])* $field $([$count])*: $lo..$hi => $fty);)*
+            }
+        }
+    );
+
+    (@mask $raw:ty, $lo:tt..$hi:tt) => (
+        <$raw>::max_value() >> (<$raw>::max_value().count_ones() - ($hi - $lo))
+    );
+
+    (@get $raw:ty, $(#[$fattr:meta])* $field:ident: $lo:tt..$hi:tt => $fty:ty) => (
+        $(#[$fattr])*
+        #[inline(always)]
+        pub fn $field(&self) -> <$fty as $crate::FieldValue<$raw>>::Read {
+            let bits = (self.0 >> $lo) & register!(@mask $raw, $lo..$hi);
+            <$fty as $crate::FieldValue<$raw>>::from_bits(bits)
+        }
+    );
+
+    (@get $raw:ty, $(#[$fattr:meta])* $field:ident[$count:tt]: $lo:tt..$hi:tt => $fty:ty) => (
+        $(#[$fattr])*
+        #[inline(always)]
+        pub fn $field(&self, i: usize) -> <$fty as $crate::FieldValue<$raw>>::Read {
+            assert!(i < $count, "bitfield index is out of bounds");
+            let shift = $lo + i * ($hi - $lo);
+            let bits = (self.0 >> shift) & register!(@mask $raw, $lo..$hi);
+            <$fty as $crate::FieldValue<$raw>>::from_bits(bits)
+        }
+    );
+
+    (@set $raw:ty, $(#[$fattr:meta])* $field:ident: $lo:tt..$hi:tt => $fty:ty) => (
+        $(#[$fattr])*
+        #[inline(always)]
+        pub fn $field(&mut self, value: $fty) -> &mut W {
+            let mask: $raw = register!(@mask $raw, $lo..$hi);
+            let bits = <$fty as $crate::FieldValue<$raw>>::into_bits(value) & mask;
+            self.0 = (self.0 & !(mask << $lo)) | (bits << $lo);
+            self
+        }
+    );
+
+    (@set $raw:ty, $(#[$fattr:meta])* $field:ident[$count:tt]: $lo:tt..$hi:tt => $fty:ty) => (
+        $(#[$fattr])*
+        #[inline(always)]
+        pub fn $field(&mut self, i: usize, value: $fty) -> &mut W {
+            assert!(i < $count, "bitfield index is out of bounds");
+            let shift = $lo + i * ($hi - $lo);
+            let mask: $raw = register!(@mask $raw, $lo..$hi);
+            let bits = <$fty as $crate::FieldValue<$raw>>::into_bits(value) & mask;
+            self.0 = (self.0 & !(mask << shift)) | (bits << shift);
+            self
+        }
+    );
+}
+
+/// A register preceded by `P`, an array of reserved padding bytes. `Padded`
+/// dereferences to the register.
+///
+/// This type is used by `register_block!` to place each register at its
+/// offset in the block.
+#[repr(C)]
+pub struct Padded<P, T> {
+    _padding: P,
+    register: T,
+}
+
+impl<P, T> Deref for Padded<P, T> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        &self.register
+    }
+}
+
+impl<P, T> DerefMut for Padded<P, T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.register
+    }
+}
+
+#[doc(hidden)]
+pub mod __private {
+    use core::marker::PhantomData;
+
+    pub use core::mem::{align_of, size_of};
+
+    /// Returns the larger of `a` and `b`.
+    pub const fn max(a: usize, b: usize) -> usize {
+        a * (a >= b) as usize + b * (a < b) as usize
+    }
+
+    /// Rounds `n` up to a multiple of `align`.
+    pub const fn align_up(n: usize, align: usize) -> usize {
+        (n + align - 1) / align * align
+    }
+
+    /// Returns the number of padding bytes needed before a register aligned
+    /// to `align` so that it lands at `offset` when the previous register
+    /// ends at `prev`. A `#[repr(C)]` struct already aligns each field, so the
+    /// padding only needs to cover the distance from the aligned position. If
+    /// the registers overlap, no padding is needed; the overlap is reported by
+    /// `StartsAfterThePreviousRegister` instead.
+    pub const fn padding(prev: usize, offset: usize, align: usize) -> usize {
+        max(offset, align_up(prev, align)) - align_up(prev, align)
+    }
+
+    /// Returns the offset at which a register of `size` bytes placed as by
+    /// `padding` ends.
+    pub const fn end(prev: usize, offset: usize, align: usize, size: usize) -> usize {
+        max(offset, align_up(prev, align)) + size
+    }
+
+    /// A condition checked about `R`, a register or a register block. `C` is
+    /// `[(); 1]` if the condition holds and `[(); 0]` if it doesn't.
+    ///
+    /// Each condition is a trait implemented only when it holds, so a failed
+    /// check is reported as an unsatisfied trait bound that names both the
+    /// condition and `R`.
+    pub struct Check<R, C>(PhantomData<(R, C)>);
+
+    macro_rules! conditions {
+        ($($condition:ident $assert:ident),*) => ($(
+            pub trait $condition {}
+            impl<R> $condition for Check<R, [(); 1]> {}
+            pub fn $assert<C: $condition>() {}
+        )*)
+    }
+
+    conditions! {
+        StartsAfterThePreviousRegister assert_starts_after_the_previous_register,
+        IsAlignedForItsType assert_is_aligned_for_its_type,
+        FitsInTheBlock assert_fits_in_the_block,
+        HasTheDeclaredSize assert_has_the_declared_size
+    }
+}
+
+/// Declares a `#[repr(C)]` struct of memory-mapped registers, each at an
+/// explicit byte offset from the start of the block.
+///
+/// Every register is written as `offset => NAME: Type`, in order of
+/// increasing offset, and the block ends with `size => @END`. Padding is
+/// inserted before each register so that it lands at its offset, and after
+/// the last register so that the struct is `size` bytes long. Registers are
+/// accessed as usual, as each field dereferences to its register.
+///
+/// Compilation fails if a register overlaps the one before it, if its offset
+/// isn't aligned for its type, or if the registers don't fit in `size` bytes.
+/// The error is an unsatisfied trait bound naming the failed condition and
+/// the register or block, such as ``the trait bound `Check<Registers::CHI::CHI,
+/// [(); 0]>: StartsAfterThePreviousRegister` is not satisfied``.
+///
+/// ```rust
+/// # #![feature(const_fn, macro_vis_matcher)]
+/// # #[macro_use]
+/// # extern crate volatile;
+/// # use volatile::{ReadVolatile, Volatile};
+/// register_block! {
+///     #[allow(non_snake_case)]
+///     struct Registers {
+///         0x00 => CS: Volatile<u32>,
+///         0x04 => CLO: ReadVolatile<u32>,
+///         0x08 => CHI: ReadVolatile<u32>,
+///         0x0C => COMPARE: [Volatile<u32>; 4],
+///         0x1C => @END,
+///     }
+/// }
+/// # fn main() {}
+/// ```
+///
+/// Registers that overlap don't compile:
+///
+/// ```compile_fail
+/// # #![feature(const_fn, macro_vis_matcher)]
+/// # #[macro_use]
+/// # extern crate volatile;
+/// # use volatile::Volatile;
+/// register_block! {
+///     struct Registers {
+///         0x00 => DATA: Volatile<u32>,
+///         0x02 => STATUS: Volatile<u16>,
+///         0x08 => @END,
+///     }
+/// }
+/// # fn main() {}
+/// ```
+///
+/// Nor does a block whose registers run past its `@END`:
+///
+/// ```compile_fail
+/// # #![feature(const_fn, macro_vis_matcher)]
+/// # #[macro_use]
+/// # extern crate volatile;
+/// # use volatile::Volatile;
+/// register_block! {
+///     struct Registers {
+///         0x00 => DATA: Volatile<u32>,
+///         0x04 => STATUS: Volatile<u32>,
+///         0x06 => @END,
+///     }
+/// }
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! register_block {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $($body:tt)*
+        }
+    ) => (
+        register_block!(@munch [$(#[$attr])* $vis struct] $name [] [] (0); $($body)*);
+    );
+
+    (@munch [$($head:tt)*] $name:ident [$($fields:tt)*] [$($checks:tt)*] ($prev:expr);
+        $offset:tt => $(#[$fattr:meta])* $fvis:vis $field:ident: $ty:ty, $($rest:tt)*
+    ) => (
+        register_block!(@munch [$($head)*] $name [
+            $($fields)*
+            $(#[$fattr])*
+            $fvis $field: $crate::Padded<
+                [u8; $crate::__private::padding($prev, $offset, $crate::__private::align_of::<$ty>())],
+                $ty
+            >,
+        ] [
+            $($checks)*
+            fn $field() {
+                struct $field;
+                $crate::__private::assert_starts_after_the_previous_register::<
+                    $crate::__private::Check<$field, [(); ($offset >= $prev) as usize]>
+                >();
+                $crate::__private::assert_is_aligned_for_its_type::<
+                    $crate::__private::Check<$field, [();
+                        ($offset % $crate::__private::align_of::<$ty>() == 0) as usize]>
+                >();
+            }
+        ] ($crate::__private::end(
+            $prev,
+            $offset,
+            $crate::__private::align_of::<$ty>(),
+            $crate::__private::size_of::<$ty>()
+        )); $($rest)*);
+    );
+
+    (@munch [$($head:tt)*] $name:ident [$($fields:tt)*] [$($checks:tt)*] ($prev:expr);
+        $size:tt => @END $(,)*
+    ) => (
+        #[repr(C)]
+        $($head)* $name {
+            $($fields)*
+            __end: [u8; $crate::__private::padding($prev, $size, 1)],
+        }
+
+        // The checks are functions that are never called: type-checking them
+        // is what evaluates each condition.
+        #[allow(dead_code, non_camel_case_types, non_snake_case, non_upper_case_globals)]
+        const $name: () = {
+            $($checks)*
+            fn __end() {
+                $crate::__private::assert_fits_in_the_block::<
+                    $crate::__private::Check<$name, [(); ($size >= $prev) as usize]>
+                >();
+                $crate::__private::assert_has_the_declared_size::<
+                    $crate::__private::Check<$name, [();
+                        ($crate::__private::size_of::<$name>() == $size) as usize]>
+                >();
+            }
+        };
+    );
+}
@@ -0,0 +1,263 @@
+use core::fmt;
+use core::iter::{DoubleEndedIterator, ExactSizeIterator, Iterator};
+use core::marker::PhantomData;
+
+#[cfg(not(feature = "mock"))]
+use core::ptr::read_volatile;
+#[cfg(feature = "mock")]
+use mock::read_volatile;
+
+use traits::*;
+
+/// A wrapper type that enforces **read-only** _volatile_ accesses to a slice
+/// of device memory, such as a receive FIFO.
+pub struct ReadVolatileSlice<'a, T: 'a> {
+    ptr: *const T,
+    len: usize,
+    _memory: PhantomData<&'a [T]>,
+}
+
+/// A wrapper type that enforces _volatile_ (read **or** write) accesses to a
+/// slice of device memory, such as a framebuffer.
+pub struct VolatileSlice<'a, T: 'a> {
+    ptr: *mut T,
+    len: usize,
+    _memory: PhantomData<&'a mut [T]>,
+}
+
+/// A wrapper type that enforces **write-only** _volatile_ accesses to a slice
+/// of device memory, such as a transmit FIFO.
+pub struct WriteVolatileSlice<'a, T: 'a> {
+    ptr: *mut T,
+    len: usize,
+    _memory: PhantomData<&'a mut [T]>,
+}
+
+impl<'a, T: 'a> ReadVolatileSlice<'a, T> {
+    /// Returns a wrapper around the `len` elements starting at `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for reads of `len` elements for `'a`.
+    pub unsafe fn from_raw_parts(ptr: *const T, len: usize) -> ReadVolatileSlice<'a, T> {
+        ReadVolatileSlice { ptr, len, _memory: PhantomData }
+    }
+}
+
+impl<'a, T: 'a> VolatileSlice<'a, T> {
+    /// Returns a wrapper around the `len` elements starting at `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for reads and writes of `len` elements for `'a`,
+    /// and must not be accessed through any other wrapper during that time.
+    pub unsafe fn from_raw_parts(ptr: *mut T, len: usize) -> VolatileSlice<'a, T> {
+        VolatileSlice { ptr, len, _memory: PhantomData }
+    }
+
+    /// Returns a wrapper around the elements in `start..end`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start > end` or if `end` is out of bounds.
+    pub fn sub_slice(&mut self, start: usize, end: usize) -> VolatileSlice<T> {
+        assert!(start <= end && end <= self.len, "volatile slice range is out of bounds");
+        unsafe { VolatileSlice::from_raw_parts(self.ptr.add(start), end - start) }
+    }
+}
+
+impl<'a, T: 'a> From<&'a mut [T]> for VolatileSlice<'a, T> {
+    /// Wraps ordinary memory, such as a buffer standing in for device memory
+    /// in a test.
+    fn from(slice: &'a mut [T]) -> VolatileSlice<'a, T> {
+        unsafe { VolatileSlice::from_raw_parts(slice.as_mut_ptr(), slice.len()) }
+    }
+}
+
+impl<'a, T: 'a> WriteVolatileSlice<'a, T> {
+    /// Returns a wrapper around the `len` elements starting at `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for writes of `len` elements for `'a`, and must not
+    /// be accessed through any other wrapper during that time.
+    pub unsafe fn from_raw_parts(ptr: *mut T, len: usize) -> WriteVolatileSlice<'a, T> {
+        WriteVolatileSlice { ptr, len, _memory: PhantomData }
+    }
+}
+
+/// Trait implemented by the array types `[T; N]` that the volatile array
+/// wrappers can hold, for every `N` up to 32 and for powers of two up to 4096.
+pub unsafe trait Array {
+    /// The type of the array's elements.
+    type Item;
+
+    /// The number of elements in the array.
+    const LEN: usize;
+
+    /// Returns a pointer to the first element.
+    fn as_ptr(&self) -> *const Self::Item;
+
+    /// Returns a mutable pointer to the first element.
+    fn as_mut_ptr(&mut self) -> *mut Self::Item;
+}
+
+macro_rules! array_lens {
+    ($($len:expr),*) => ($(
+        unsafe impl<T> Array for [T; $len] {
+            type Item = T;
+            const LEN: usize = $len;
+            #[inline(always)] fn as_ptr(&self) -> *const T { self[..].as_ptr() }
+            #[inline(always)] fn as_mut_ptr(&mut self) -> *mut T { self[..].as_mut_ptr() }
+        }
+    )*)
+}
+
+array_lens!(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19,
+            20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 64, 128, 256, 512,
+            1024, 2048, 4096);
+
+/// A wrapper type that enforces **read-only** _volatile_ accesses to an array
+/// `A` of values, such as `[u32; 8]`.
+#[repr(C)]
+pub struct ReadVolatileArray<A: Array>(A);
+
+/// A wrapper type that enforces _volatile_ (read **or** write) accesses to an
+/// array `A` of values, such as `[u32; 8]`.
+#[repr(C)]
+pub struct VolatileArray<A: Array>(A);
+
+/// A wrapper type that enforces **write-only** _volatile_ accesses to an
+/// array `A` of values, such as `[u32; 8]`.
+#[repr(C)]
+pub struct WriteVolatileArray<A: Array>(A);
+
+impl<A: Array> VolatileArray<A> {
+    /// Returns a wrapper around the whole array.
+    pub fn as_volatile_slice(&mut self) -> VolatileSlice<A::Item> {
+        unsafe { VolatileSlice::from_raw_parts(self.0.as_mut_ptr(), A::LEN) }
+    }
+}
+
+macro_rules! slice_impls {
+    ($($type:ident),*) => ($(
+        impl<'a, T: 'a> SliceWrapper for $type<'a, T> {
+            type Elem = T;
+            #[inline(always)] fn len(&self) -> usize { self.len }
+        }
+
+        impl<'a, T: 'a> fmt::Debug for $type<'a, T> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_struct(stringify!($type))
+                    .field("address", &self.ptr)
+                    .field("len", &self.len)
+                    .finish()
+            }
+        }
+
+        unsafe impl<'a, T: Send + 'a> Send for $type<'a, T> {  }
+    )*)
+}
+
+macro_rules! array_impls {
+    ($($type:ident),*) => ($(
+        impl<A: Array> SliceWrapper for $type<A> {
+            type Elem = A::Item;
+            #[inline(always)] fn len(&self) -> usize { A::LEN }
+        }
+
+        impl<A: Array> fmt::Debug for $type<A> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_struct(stringify!($type))
+                    .field("address", &self.0.as_ptr())
+                    .field("len", &A::LEN)
+                    .finish()
+            }
+        }
+
+        impl<A: Array> !Sync for $type<A> {  }
+    )*)
+}
+
+slice_impls!(ReadVolatileSlice, VolatileSlice, WriteVolatileSlice);
+array_impls!(ReadVolatileArray, VolatileArray, WriteVolatileArray);
+
+impl<'a, T: Copy + 'a> ReadableSlice<T> for ReadVolatileSlice<'a, T> {
+    #[inline(always)] fn as_ptr(&self) -> *const T { self.ptr }
+}
+
+impl<'a, T: Copy + 'a> ReadableSlice<T> for VolatileSlice<'a, T> {
+    #[inline(always)] fn as_ptr(&self) -> *const T { self.ptr }
+}
+
+impl<'a, T: Copy + 'a> WriteableSlice<T> for VolatileSlice<'a, T> {
+    #[inline(always)] fn as_mut_ptr(&mut self) -> *mut T { self.ptr }
+}
+
+impl<'a, T: Copy + 'a> WriteableSlice<T> for WriteVolatileSlice<'a, T> {
+    #[inline(always)] fn as_mut_ptr(&mut self) -> *mut T { self.ptr }
+}
+
+impl<T: Copy, A: Array<Item = T>> ReadableSlice<T> for ReadVolatileArray<A> {
+    #[inline(always)] fn as_ptr(&self) -> *const T { self.0.as_ptr() }
+}
+
+impl<T: Copy, A: Array<Item = T>> ReadableSlice<T> for VolatileArray<A> {
+    #[inline(always)] fn as_ptr(&self) -> *const T { self.0.as_ptr() }
+}
+
+impl<T: Copy, A: Array<Item = T>> WriteableSlice<T> for VolatileArray<A> {
+    #[inline(always)] fn as_mut_ptr(&mut self) -> *mut T { self.0.as_mut_ptr() }
+}
+
+impl<T: Copy, A: Array<Item = T>> WriteableSlice<T> for WriteVolatileArray<A> {
+    #[inline(always)] fn as_mut_ptr(&mut self) -> *mut T { self.0.as_mut_ptr() }
+}
+
+/// An iterator that reads each element of a volatile slice wrapper, in order.
+///
+/// This `struct` is created by the `iter` method on `ReadableSlice`.
+pub struct VolatileIter<'s, T: 's> {
+    ptr: *const T,
+    front: usize,
+    back: usize,
+    _memory: PhantomData<&'s [T]>,
+}
+
+impl<'s, T: 's> VolatileIter<'s, T> {
+    /// Returns an iterator over the `len` elements starting at `ptr`.
+    pub(crate) unsafe fn new(ptr: *const T, len: usize) -> VolatileIter<'s, T> {
+        VolatileIter { ptr, front: 0, back: len, _memory: PhantomData }
+    }
+}
+
+impl<'s, T: Copy + 's> Iterator for VolatileIter<'s, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.front == self.back {
+            None
+        } else {
+            self.front += 1;
+            Some(unsafe { read_volatile(self.ptr.add(self.front - 1)) })
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<'s, T: Copy + 's> DoubleEndedIterator for VolatileIter<'s, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.front == self.back {
+            None
+        } else {
+            self.back -= 1;
+            Some(unsafe { read_volatile(self.ptr.add(self.back)) })
+        }
+    }
+}
+
+impl<'s, T: Copy + 's> ExactSizeIterator for VolatileIter<'s, T> {}
@@ -0,0 +1,154 @@
+use core::mem;
+
+use prelude::*;
+use {ReadVolatile, ReadVolatileSlice, Volatile, VolatileArray, VolatileSlice, WriteVolatileSlice};
+
+#[test]
+fn slice_read_write() {
+    let mut memory = [0u32; 4];
+    let mut slice = unsafe { VolatileSlice::from_raw_parts(memory.as_mut_ptr(), 4) };
+    assert_eq!(slice.len(), 4);
+    slice.write(1, 7);
+    assert_eq!(slice.read(1), 7);
+    assert_eq!(slice.get(3), Some(0));
+    assert_eq!(slice.get(4), None);
+
+    slice.fill(9);
+    slice.sub_slice(2, 4).copy_from_slice(&[1, 2]);
+    let mut copy = [0; 4];
+    slice.copy_into_slice(&mut copy);
+    assert_eq!(copy, [9, 9, 1, 2]);
+    assert_eq!(
+        slice.iter().rev().collect::<::std::vec::Vec<_>>(),
+        [2, 1, 9, 9]
+    );
+    assert_eq!(memory, [9, 9, 1, 2]);
+}
+
+#[test]
+fn slice_from_memory() {
+    let mut memory = [0u8; 3];
+    VolatileSlice::from(&mut memory[1..]).fill(4);
+    assert_eq!(memory, [0, 4, 4]);
+}
+
+#[test]
+#[should_panic]
+fn slice_bounds_checked() {
+    let memory = [0u8; 2];
+    let slice = unsafe { ReadVolatileSlice::from_raw_parts(memory.as_ptr(), 2) };
+    slice.read(2);
+}
+
+#[test]
+#[should_panic]
+fn copy_length_checked() {
+    let mut memory = [0u8; 2];
+    let mut slice = unsafe { WriteVolatileSlice::from_raw_parts(memory.as_mut_ptr(), 2) };
+    slice.copy_from_slice(&[1, 2, 3]);
+}
+
+#[test]
+fn array_in_place() {
+    let mut memory = [1u16, 2, 3];
+    let array = unsafe { &mut *(memory.as_mut_ptr() as *mut VolatileArray<[u16; 3]>) };
+    assert_eq!(array.iter().sum::<u16>(), 6);
+    array.as_volatile_slice().write(0, 5);
+    array.write(2, 4);
+    assert_eq!(array.iter().len(), 3);
+    assert_eq!(memory, [5, 2, 4]);
+}
+
+bitfield_enum! {
+    #[derive(Debug, PartialEq)]
+    #[repr(u8)]
+    pub enum Mode: u32 {
+        Off = 0b00,
+        Slow = 0b01,
+        Fast = 0b11,
+    }
+}
+
+register! {
+    mod ctrl: u32 {
+        enable: 0..1 => bool,
+        mode: 1..3 => Mode,
+        count: 4..12 => u32,
+        irq[4]: 16..17 => bool,
+        level[2]: 24..28 => u32,
+    }
+}
+
+/// Returns `memory` as a `ctrl` register.
+fn ctrl(memory: &mut u32) -> &mut ctrl::Register<Volatile<u32>> {
+    unsafe { &mut *(memory as *mut u32 as *mut ctrl::Register<Volatile<u32>>) }
+}
+
+#[test]
+fn register_fields_read_their_bits() {
+    let mut memory = 0xC30A_0A53;
+    let r = ctrl(&mut memory).read();
+    assert_eq!(r.bits(), 0xC30A_0A53);
+    assert!(r.enable());
+    assert_eq!(r.mode(), Some(Mode::Slow));
+    assert_eq!(r.count(), 0xA5);
+    let irqs: ::std::vec::Vec<_> = (0..4).map(|i| r.irq(i)).collect();
+    assert_eq!(irqs, [false, true, false, true]);
+    assert_eq!((r.level(0), r.level(1)), (0x3, 0xC));
+}
+
+#[test]
+fn register_write_starts_from_zero() {
+    let mut memory = 0xFFFF_FFFF;
+    ctrl(&mut memory).write(|w| w.mode(Mode::Fast).count(0x1A5).irq(3, true));
+    assert_eq!(memory, 0x0008_0A56);
+}
+
+#[test]
+fn register_modify_preserves_other_bits() {
+    let mut memory = 0xFFFF_FFFF;
+    ctrl(&mut memory).modify(|r, w| {
+        assert_eq!(r.count(), 0xFF);
+        w.enable(false).irq(2, false).level(1, 0x5)
+    });
+    assert_eq!(memory, 0x5FFB_FFFE);
+}
+
+#[test]
+fn register_enum_without_variant_reads_as_none() {
+    let mut memory = 0b10 << 1;
+    assert_eq!(ctrl(&mut memory).read().mode(), None);
+    ctrl(&mut memory).modify(|_, w| w.mode(Mode::Off));
+    assert_eq!(ctrl(&mut memory).read().mode(), Some(Mode::Off));
+}
+
+#[test]
+#[should_panic]
+fn register_field_index_checked() {
+    let mut memory = 0;
+    ctrl(&mut memory).read().irq(4);
+}
+
+register_block! {
+    #[allow(non_snake_case)]
+    struct Block {
+        0x00 => FLAGS: Volatile<u8>,
+        0x04 => DATA: Volatile<u32>,
+        0x10 => FIFO: [ReadVolatile<u16>; 2],
+        0x20 => @END,
+    }
+}
+
+#[test]
+fn register_block_layout() {
+    let block: Block = unsafe { mem::zeroed() };
+    let offset = |register: usize| register - &block as *const Block as usize;
+    assert_eq!(offset(&*block.FLAGS as *const _ as usize), 0x00);
+    assert_eq!(offset(&*block.DATA as *const _ as usize), 0x04);
+    assert_eq!(offset(&*block.FIFO as *const _ as usize), 0x10);
+
+    // `#[repr(C)]` aligns `DATA` by itself; `FIFO` needs 8 bytes of padding.
+    assert_eq!(mem::size_of_val(&block.DATA), 4);
+    assert_eq!(mem::size_of_val(&block.FIFO), 8 + 4);
+    assert_eq!(mem::size_of::<Block>(), 0x20);
+}
@@ -1,3 +1,8 @@
+#[cfg(not(feature = "mock"))]
+use core::ptr::{read_volatile, write_volatile};
+#[cfg(feature = "mock")]
+use mock::{read_volatile, write_volatile};
+
 /// Trait implemented by all of the wrapper types in this crate.
 ///
 /// The inner type of wrapper is specified as an associated constant `Inner`.
@@ -20,7 +25,7 @@ pub trait Readable<T> {
     /// done using volatile semantics.
     #[inline(always)]
     fn read(&self) -> T {
-        unsafe { ::core::ptr::read_volatile(self.inner()) }
+        unsafe { read_volatile(self.inner()) }
     }
 
     /// Returns `true` if the value pointed to by `self` has the mask `mask`.
@@ -44,7 +49,7 @@ pub trait Writeable<T> {
     /// always done using volatile semantics.
     #[inline(always)]
     fn write(&mut self, val: T) {
-        unsafe { ::core::ptr::write_volatile(self.inner(), val) }
+        unsafe { write_volatile(self.inner(), val) }
     }
 }
 
@@ -68,3 +73,104 @@ pub trait ReadableWriteable<T>: Readable<T> + Writeable<T>
     }
 }
 
+
+/// Trait implemented by all of the slice wrapper types in this crate.
+pub trait SliceWrapper {
+    /// The type of the wrapped elements.
+    type Elem;
+
+    /// Returns the number of wrapped elements.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if there are no wrapped elements.
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Trait implemented by **readable** volatile slice wrappers.
+pub trait ReadableSlice<T: Copy>: SliceWrapper<Elem = T> {
+    /// Returns a pointer to the first element.
+    fn as_ptr(&self) -> *const T;
+
+    /// Reads and returns the element at `index`. The read is always done
+    /// using volatile semantics.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    #[inline(always)]
+    fn read(&self, index: usize) -> T {
+        assert!(index < self.len(), "volatile slice index is out of bounds");
+        unsafe { read_volatile(self.as_ptr().add(index)) }
+    }
+
+    /// Reads and returns the element at `index`, or returns `None` if `index`
+    /// is out of bounds.
+    #[inline(always)]
+    fn get(&self, index: usize) -> Option<T> {
+        if index < self.len() {
+            Some(self.read(index))
+        } else {
+            None
+        }
+    }
+
+    /// Reads every element, in order, into `dst`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `dst` isn't as long as `self`.
+    fn copy_into_slice(&self, dst: &mut [T]) {
+        assert_eq!(self.len(), dst.len(), "volatile slice lengths differ");
+        for (i, slot) in dst.iter_mut().enumerate() {
+            *slot = unsafe { read_volatile(self.as_ptr().add(i)) };
+        }
+    }
+
+    /// Returns an iterator that reads each element, in order.
+    #[inline(always)]
+    fn iter(&self) -> ::slice::VolatileIter<T> {
+        unsafe { ::slice::VolatileIter::new(self.as_ptr(), self.len()) }
+    }
+}
+
+/// Trait implemented by **writeable** volatile slice wrappers.
+pub trait WriteableSlice<T: Copy>: SliceWrapper<Elem = T> {
+    /// Returns a mutable pointer to the first element.
+    fn as_mut_ptr(&mut self) -> *mut T;
+
+    /// Writes `val` to the element at `index`. The write is always done using
+    /// volatile semantics.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    #[inline(always)]
+    fn write(&mut self, index: usize, val: T) {
+        assert!(index < self.len(), "volatile slice index is out of bounds");
+        unsafe { write_volatile(self.as_mut_ptr().add(index), val) }
+    }
+
+    /// Writes every element of `src`, in order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src` isn't as long as `self`.
+    fn copy_from_slice(&mut self, src: &[T]) {
+        assert_eq!(self.len(), src.len(), "volatile slice lengths differ");
+        let ptr = self.as_mut_ptr();
+        for (i, &val) in src.iter().enumerate() {
+            unsafe { write_volatile(ptr.add(i), val) }
+        }
+    }
+
+    /// Writes `val` to every element.
+    fn fill(&mut self, val: T) {
+        let ptr = self.as_mut_ptr();
+        for i in 0..self.len() {
+            unsafe { write_volatile(ptr.add(i), val) }
+        }
+    }
+}